@@ -10,6 +10,7 @@
 , inputs
 , name
 , lockFile
+, features ? [ ]
 , ...
 }:
 let
@@ -42,6 +43,7 @@ rustPlatform.buildRustPackage {
   RUST_BACKTRACE = "full";
   copyLibs = true;
   cargoBuildFlags = [ "-p ${name}" ];
+  buildFeatures = features;
   buildAndTestSubdir = "plugins/${name}";
 
   meta = with lib; {
@@ -0,0 +1,537 @@
+use std::{env, fmt, path::Path};
+
+/// A parsed [desktop file](https://specifications.freedesktop.org/desktop-entry-spec/latest/):
+/// its groups of `Key[locale]=value` entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopFile {
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    key: String,
+    locale: Option<String>,
+    /// The value as written, unescaped on access since lists unescape differently
+    value: String,
+}
+
+impl DesktopFile {
+    /// Parse the content of a desktop file.
+    ///
+    /// Like GLib, lines that are neither a group header, an entry nor a comment make the whole
+    /// file invalid.
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let mut groups = Vec::<Group>::new();
+        for (i, line) in content.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .filter(|name| !name.contains(['[', ']']) && !name.is_empty())
+                    .ok_or(Error::InvalidLine(line_number))?;
+                if groups.iter().any(|group| group.name == name) {
+                    return Err(Error::DuplicateGroup(name.to_string()));
+                }
+                groups.push(Group {
+                    name: name.to_string(),
+                    entries: Vec::new(),
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(Error::InvalidLine(line_number))?;
+            let (key, locale) = match key.trim_end().split_once('[') {
+                Some((key, locale)) => {
+                    let locale = locale
+                        .strip_suffix(']')
+                        .filter(|locale| !locale.is_empty())
+                        .ok_or(Error::InvalidLine(line_number))?;
+                    (key, Some(locale.to_string()))
+                }
+                None => (key.trim_end(), None),
+            };
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|chr| chr.is_ascii_alphanumeric() || chr == '-')
+            {
+                return Err(Error::InvalidLine(line_number));
+            }
+
+            let group = groups
+                .last_mut()
+                .ok_or(Error::EntryOutsideGroup(line_number))?;
+            group.entries.push(Entry {
+                key: key.to_string(),
+                locale,
+                value: value.trim_start().to_string(),
+            });
+        }
+        Ok(Self { groups })
+    }
+
+    /// The group with header `[name]`.
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+impl Group {
+    /// The raw, still escaped value of the unlocalized `key`.
+    ///
+    /// If a key appears more than once the last one counts.
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.key == key && entry.locale.is_none())
+            .map(|entry| entry.value.as_str())
+    }
+
+    /// The value of `key` translated to `locale`, falling back to the unlocalized one.
+    pub fn raw_localized(&self, key: &str, locale: Option<&Locale>) -> Option<&str> {
+        let Some(locale) = locale else {
+            return self.raw(key);
+        };
+        locale
+            .candidates()
+            .iter()
+            .find_map(|candidate| {
+                self.entries
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.key == key && entry.locale.as_ref() == Some(candidate))
+            })
+            .map(|entry| entry.value.as_str())
+            .or_else(|| self.raw(key))
+    }
+
+    /// The localestring `key` translated to `locale`, see [`string`](Self::string).
+    pub fn localized(&self, key: &str, locale: Option<&Locale>) -> Option<String> {
+        self.raw_localized(key, locale)
+            .map(|value| unescape(value, false))
+    }
+
+    /// The localestring list `key` translated to `locale`, see [`strings`](Self::strings).
+    pub fn localized_list(&self, key: &str, locale: Option<&Locale>) -> Option<Vec<String>> {
+        self.raw_localized(key, locale).map(split_list)
+    }
+
+    /// The unlocalized string `key`, with `\s`, `\n`, `\t`, `\r` and `\\` unescaped.
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.raw(key).map(|value| unescape(value, false))
+    }
+
+    /// The `;` separated list `key`, a `\;` is part of an item.
+    pub fn strings(&self, key: &str) -> Option<Vec<String>> {
+        self.raw(key).map(split_list)
+    }
+
+    /// The boolean `key`, `false` if it is missing.
+    pub fn boolean(&self, key: &str) -> Result<bool, Error> {
+        match self.raw(key) {
+            None | Some("false") => Ok(false),
+            Some("true") => Ok(true),
+            // Accepted by GLib for compatibility with old files
+            Some("0") => Ok(false),
+            Some("1") => Ok(true),
+            Some(value) => Err(Error::InvalidBool {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Unescape a value, `list` also unescapes `\;`.
+fn unescape(value: &str, list: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(chr) = chars.next() {
+        if chr != '\\' {
+            out.push(chr);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(';') if list => out.push(';'),
+            // Not an escape sequence, kept as written
+            Some(chr) => {
+                out.push('\\');
+                out.push(chr);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, chr) in value.char_indices() {
+        match chr {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                items.push(unescape(&value[start..i], true));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    // The trailing `;` is optional
+    if start < value.len() {
+        items.push(unescape(&value[start..], true));
+    }
+    items
+}
+
+/// A POSIX locale, `lang_COUNTRY.ENCODING@MODIFIER` with everything but the language optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    lang: String,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    /// The locale messages are shown in, from `LC_ALL`, `LC_MESSAGES` or `LANG`.
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(&value))
+    }
+
+    /// `None` for the `C` and `POSIX` locales, which have no translations.
+    pub fn parse(locale: &str) -> Option<Self> {
+        let (locale, modifier) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier.to_string())),
+            None => (locale, None),
+        };
+        // The encoding does not matter for matching
+        let locale = locale.split('.').next().unwrap_or(locale);
+        let (lang, country) = match locale.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (locale, None),
+        };
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return None;
+        }
+        Some(Self {
+            lang: lang.to_string(),
+            country,
+            modifier,
+        })
+    }
+
+    /// The locale keys that are looked for, best first: `lang_COUNTRY@MODIFIER`,
+    /// `lang_COUNTRY`, `lang@MODIFIER` and `lang`.
+    pub fn candidates(&self) -> Vec<String> {
+        let mut candidates = Vec::new();
+        if let (Some(country), Some(modifier)) = (&self.country, &self.modifier) {
+            candidates.push(format!("{}_{}@{}", self.lang, country, modifier));
+        }
+        if let Some(country) = &self.country {
+            candidates.push(format!("{}_{}", self.lang, country));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{}@{}", self.lang, modifier));
+        }
+        candidates.push(self.lang.clone());
+        candidates
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The most specific candidate is the locale itself
+        f.write_str(&self.candidates()[0])
+    }
+}
+
+/// What the field codes of an `Exec` value expand to. Files and URLs are never passed, so their
+/// codes expand to nothing.
+pub struct FieldCodes<'a> {
+    /// `%i`, expanded to `--icon <icon>`
+    pub icon: Option<&'a str>,
+    /// `%c`, the translated name of the application
+    pub name: &'a str,
+    /// `%k`, the desktop file
+    pub file: &'a Path,
+}
+
+/// Split an unescaped `Exec` value into the program and its arguments and expand its field
+/// codes, following the quoting rules of the spec.
+pub fn exec_args(exec: &str, codes: &FieldCodes) -> Result<Vec<String>, Error> {
+    let invalid = |why: &str| Error::InvalidExec(format!("{} in `{}`", why, exec));
+    let mut args = Vec::new();
+    // `None` between arguments, so a field code expanding to nothing leaves no empty argument
+    let mut arg: Option<String> = None;
+    let mut chars = exec.chars();
+    while let Some(chr) = chars.next() {
+        match chr {
+            ' ' | '\t' | '\n' => args.extend(arg.take()),
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(chr @ ('"' | '`' | '$' | '\\')) => arg.push(chr),
+                            Some(chr) => {
+                                arg.push('\\');
+                                arg.push(chr);
+                            }
+                            None => return Err(invalid("Unterminated quote")),
+                        },
+                        Some(chr) => arg.push(chr),
+                        None => return Err(invalid("Unterminated quote")),
+                    }
+                }
+            }
+            '%' => match chars.next() {
+                Some('%') => arg.get_or_insert_with(String::new).push('%'),
+                // Files and URLs, and the deprecated codes
+                Some('f' | 'F' | 'u' | 'U' | 'd' | 'D' | 'n' | 'N' | 'v' | 'm') => {}
+                Some('i') => {
+                    if let Some(icon) = codes.icon.filter(|icon| !icon.is_empty()) {
+                        args.extend(arg.take());
+                        args.extend(["--icon".to_string(), icon.to_string()]);
+                    }
+                }
+                Some('c') => arg.get_or_insert_with(String::new).push_str(codes.name),
+                Some('k') => arg
+                    .get_or_insert_with(String::new)
+                    .push_str(&codes.file.to_string_lossy()),
+                Some(chr) => return Err(invalid(&format!("Unknown field code %{}", chr))),
+                None => return Err(invalid("Incomplete field code")),
+            },
+            chr => arg.get_or_insert_with(String::new).push(chr),
+        }
+    }
+    args.extend(arg);
+
+    if args.first().is_none_or(String::is_empty) {
+        return Err(invalid("No program"));
+    }
+    Ok(args)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A line that is neither a group header, an entry nor a comment
+    InvalidLine(usize),
+    EntryOutsideGroup(usize),
+    DuplicateGroup(String),
+    InvalidBool {
+        key: String,
+        value: String,
+    },
+    InvalidExec(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidLine(line) => write!(f, "Invalid line {}", line),
+            Error::EntryOutsideGroup(line) => {
+                write!(f, "Entry outside of a group on line {}", line)
+            }
+            Error::DuplicateGroup(name) => write!(f, "Duplicate group [{}]", name),
+            Error::InvalidBool { key, value } => {
+                write!(f, "Invalid boolean `{}` for {}", value, key)
+            }
+            Error::InvalidExec(why) => write!(f, "Invalid Exec: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Result<DesktopFile, Error> {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        DesktopFile::parse(&std::fs::read_to_string(path).unwrap())
+    }
+
+    #[test]
+    fn test_groups_and_locales() {
+        let file = parse("firefox.desktop").unwrap();
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(entry.string("Name").as_deref(), Some("Firefox"));
+        assert_eq!(entry.string("GenericName").as_deref(), Some("Web Browser"));
+        assert_eq!(
+            entry.strings("Actions"),
+            Some(vec!["new-window".into(), "new-private-window".into()])
+        );
+        // `Exec` contains another `=`, localized keys do not override the plain one
+        assert_eq!(
+            entry.string("Exec").as_deref(),
+            Some("env MOZ_ENABLE_WAYLAND=1 firefox %u")
+        );
+
+        let action = file.group("Desktop Action new-private-window").unwrap();
+        assert_eq!(action.string("Name").as_deref(), Some("New Private Window"));
+    }
+
+    #[test]
+    fn test_locales() {
+        let file = parse("locales.desktop").unwrap();
+        let entry = file.group("Desktop Entry").unwrap();
+        let name = |locale: &str| entry.localized("Name", Locale::parse(locale).as_ref());
+
+        assert_eq!(name("sr_RS.UTF-8@latin").as_deref(), Some("sr_RS"));
+        assert_eq!(name("sr_ME@latin").as_deref(), Some("sr@latin"));
+        assert_eq!(name("sr_ME").as_deref(), Some("sr"));
+        assert_eq!(name("pt_BR.UTF-8").as_deref(), Some("pt_BR"));
+        assert_eq!(name("pt_PT").as_deref(), Some("Untranslated"));
+        assert_eq!(name("C.UTF-8").as_deref(), Some("Untranslated"));
+        assert_eq!(
+            entry.localized("Name", None).as_deref(),
+            Some("Untranslated")
+        );
+        assert_eq!(
+            entry.localized_list("Keywords", Locale::parse("de_DE").as_ref()),
+            Some(vec!["Schlüssel".into(), "Wort".into()])
+        );
+
+        assert_eq!(
+            Locale::parse("sr_RS.UTF-8@latin").unwrap().to_string(),
+            "sr_RS@latin"
+        );
+        assert_eq!(
+            Locale::parse("de@euro").unwrap().candidates(),
+            ["de@euro", "de"]
+        );
+    }
+
+    #[test]
+    fn test_whitespace_and_comments() {
+        // CRLF line endings, indented lines, spaces around `=` and a trailing `\s`
+        let file = parse("spaces.desktop").unwrap();
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(entry.string("Name").as_deref(), Some("Spaced Out "));
+        assert_eq!(entry.string("Exec").as_deref(), Some("spaced --flag"));
+        assert_eq!(entry.string("Icon").as_deref(), Some("spaced"));
+        assert_eq!(entry.boolean("Terminal"), Ok(true));
+        assert_eq!(entry.string("Comment"), None);
+    }
+
+    #[test]
+    fn test_escapes() {
+        let file = parse("escapes.desktop").unwrap();
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(
+            entry.string("Comment").as_deref(),
+            Some("Two\nlines\tand a \\ backslash")
+        );
+        assert_eq!(
+            entry.strings("Keywords"),
+            Some(vec![
+                "semi;colon".into(),
+                "back\\slash".into(),
+                "plain".into()
+            ])
+        );
+        assert_eq!(entry.strings("Categories"), Some(vec!["Utility".into()]));
+        assert_eq!(entry.string("X-Unknown").as_deref(), Some("C:\\path\\q"));
+    }
+
+    #[test]
+    fn test_booleans() {
+        let file = parse("booleans.desktop").unwrap();
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(entry.boolean("NoDisplay"), Ok(false));
+        assert_eq!(entry.boolean("StartupNotify"), Ok(true));
+        assert_eq!(entry.boolean("Hidden"), Ok(false));
+        assert_eq!(
+            entry.boolean("Terminal"),
+            Err(Error::InvalidBool {
+                key: "Terminal".into(),
+                value: "yes".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_exec_args() {
+        let codes = FieldCodes {
+            icon: Some("firefox"),
+            name: "Firefox",
+            file: Path::new("/usr/share/applications/firefox.desktop"),
+        };
+        let args = |exec: &str| exec_args(exec, &codes);
+
+        assert_eq!(args("firefox %u"), Ok(vec!["firefox".into()]));
+        assert_eq!(
+            args("app %i --name=%c --file=%F 100%%"),
+            Ok(vec![
+                "app".into(),
+                "--icon".into(),
+                "firefox".into(),
+                "--name=Firefox".into(),
+                "--file=".into(),
+                "100%".into(),
+            ])
+        );
+        assert_eq!(
+            args(r#"sh -c "echo \"hi\" \$HOME \\n" ""  %k"#),
+            Ok(vec![
+                "sh".into(),
+                "-c".into(),
+                r#"echo "hi" $HOME \n"#.into(),
+                "".into(),
+                "/usr/share/applications/firefox.desktop".into(),
+            ])
+        );
+        assert_eq!(
+            args(r#""/opt/My App/app" --flag"#),
+            Ok(vec!["/opt/My App/app".into(), "--flag".into()])
+        );
+
+        assert!(args(r#"app "unterminated"#).is_err());
+        assert!(args("app %x").is_err());
+        assert!(args("%f").is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(parse("invalid-line.desktop"), Err(Error::InvalidLine(4)));
+        assert_eq!(
+            DesktopFile::parse("Name=Orphan\n[Desktop Entry]\n"),
+            Err(Error::EntryOutsideGroup(1))
+        );
+        assert_eq!(
+            DesktopFile::parse("[Desktop Entry]\n[Desktop Entry]\n"),
+            Err(Error::DuplicateGroup("Desktop Entry".into()))
+        );
+        assert_eq!(
+            DesktopFile::parse("[Desktop Entry]\nName[de=Kaputt\n"),
+            Err(Error::InvalidLine(2))
+        );
+    }
+}
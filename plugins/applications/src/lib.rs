@@ -1,16 +1,52 @@
 use abi_stable::std_types::{ROption, RString, RVec};
 use anyrun_plugin::{anyrun_interface::HandleResult, *};
-use scrubber::NucleoEntry;
+use desktop_file::Locale;
+use scrubber::{NucleoEntry, SearchField};
 use serde::Deserialize;
-use std::{env, fs, process::Command};
+use std::{
+    collections::BTreeMap, io, os::unix::ffi::OsStrExt, path::Path, process::Command,
+    time::Duration,
+};
 
-use utils::fuzzy_match;
+use utils::{
+    actions::Actions,
+    cache,
+    config::Watched,
+    frecency::{self, Frecency},
+    highlight,
+    ids::Registry,
+    log,
+    rank::{self, EmptyQuery, SortKeys},
+    refresh::Refreshed,
+    report, search_with,
+    spawn::{self, Launcher, Spawn},
+    Field, SearchOptions, Searchable,
+};
 
 #[derive(Deserialize)]
+#[serde(default)]
 pub struct Config {
     desktop_actions: bool,
     max_entries: usize,
+    /// Seconds between rescans of the desktop entries, 0 disables them. Read at startup.
+    refresh_interval: u64,
     terminal: Option<String>,
+    /// How applications are started, e.g. in their own systemd scope
+    launcher: Launcher,
+    /// Environment variables set for launched applications
+    env: BTreeMap<String, String>,
+    highlight_matches: bool,
+    /// Which parts of the desktop entries are searched
+    search_fields: Vec<SearchField>,
+    /// Templates of the description, the first one with all its placeholders known is used
+    description: Vec<String>,
+    /// What is listed before anything is typed
+    empty_query: EmptyQuery,
+    /// Weak matches left out, tie-breaks, transliteration and unicode folding
+    search: SearchOptions,
+    frecency_weight: u32,
+    /// Log level and file, read at startup
+    log: log::Config,
 }
 
 impl Default for Config {
@@ -18,113 +54,271 @@ impl Default for Config {
         Self {
             desktop_actions: false,
             max_entries: 5,
+            refresh_interval: 60,
             terminal: Some("wezterm".into()),
+            launcher: Launcher::Direct,
+            env: BTreeMap::new(),
+            highlight_matches: false,
+            search_fields: scrubber::ALL_SEARCH_FIELDS.to_vec(),
+            description: vec!["{comment}".into(), "{generic}".into()],
+            empty_query: EmptyQuery::None,
+            search: SearchOptions::default(),
+            frecency_weight: frecency::DEFAULT_WEIGHT,
+            log: log::Config::default(),
         }
     }
 }
 
+/// A desktop entry searched by the fields enabled in the config.
+struct Searched<'a> {
+    entry: &'a NucleoEntry,
+    fields: &'a [SearchField],
+}
+
+impl Searchable for Searched<'_> {
+    fn fields(&self) -> Vec<Field<'_>> {
+        self.entry.search_fields(self.fields)
+    }
+}
+
 pub struct State {
-    config: Config,
-    entries: Vec<NucleoEntry>,
+    config: Watched<Config>,
+    entries: Refreshed<Registry<NucleoEntry>>,
+    frecency: Frecency,
 }
 
+mod desktop_file;
 mod scrubber;
 
 const SENSIBLE_TERMINALS: &[&str] = &["alacritty", "foot", "kitty", "wezterm", "wterm"];
 
+/// What happens to the selected entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Launch,
+    Terminal,
+    ShowFile,
+}
+
+fn actions() -> Actions<Action> {
+    Actions::new(Action::Launch)
+        .action("term", "Open in terminal", Action::Terminal)
+        .action("files", "Show in file manager", Action::ShowFile)
+}
+
 #[handler]
 pub fn handler(selection: Match, state: &State) -> HandleResult {
-    let entry = state
-        .entries
-        .iter()
-        .find(|entry| entry.id == selection.id.unwrap())
-        .unwrap();
+    if report::is_report(&selection) {
+        return HandleResult::Close;
+    }
+    let entries = state.entries.get();
+    let (entry, action) =
+        match actions().resolve(selection.id.into_option(), |id| entries.get(Some(id)).ok()) {
+            Ok(entry) => entry,
+            Err(why) => {
+                log::error!("Error running desktop entry: {}", why);
+                return HandleResult::Close;
+            }
+        };
 
     let desktop_entry = &entry.desktop_entry;
+    if action == Action::ShowFile {
+        show_file(&desktop_entry.file);
+        return HandleResult::Close;
+    }
 
-    if desktop_entry.term {
-        match &state.config.terminal {
-            Some(term) => {
-                if let Err(why) = Command::new(term)
-                    .arg("-e")
-                    .arg(&desktop_entry.exec)
-                    .spawn()
-                {
-                    eprintln!("Error running desktop entry: {}", why);
-                }
-            }
-            None => {
-                for term in SENSIBLE_TERMINALS {
-                    if Command::new(term)
-                        .arg("-e")
-                        .arg(&desktop_entry.exec)
-                        .spawn()
-                        .is_ok()
-                    {
-                        break;
-                    }
-                }
-            }
+    state.frecency.record(&desktop_entry.desktop_id);
+
+    let config = state.config.get();
+    let launch = |command: Spawn| {
+        let command = command.envs(&config.env).launcher(&config.launcher);
+        match &desktop_entry.path {
+            Some(path) => command.current_dir(path),
+            None => command,
         }
-    } else if let Err(why) = Command::new("sh")
-        .arg("-c")
-        .arg(&desktop_entry.exec)
-        .current_dir(
-            desktop_entry
-                .path
-                .as_ref()
-                .unwrap_or(&env::current_dir().unwrap()),
-        )
         .spawn()
-    {
-        eprintln!("Error running desktop entry: {}", why);
+    };
+    let launched = if desktop_entry.term || action == Action::Terminal {
+        let in_terminal = |term: &str| launch(Spawn::new(term).arg("-e").args(&desktop_entry.exec));
+        match &config.terminal {
+            Some(term) => in_terminal(term),
+            // Only a missing terminal is worth trying the next one for
+            None => SENSIBLE_TERMINALS
+                .iter()
+                .map(|term| in_terminal(term))
+                .find(|launched| {
+                    !matches!(launched, Err(why) if why.why.kind() == io::ErrorKind::NotFound)
+                })
+                .unwrap_or_else(|| {
+                    Err(spawn::Error {
+                        program: SENSIBLE_TERMINALS.join(", "),
+                        why: io::ErrorKind::NotFound.into(),
+                    })
+                }),
+        }
+    } else {
+        // Desktop entries always have a program
+        launch(Spawn::new(&desktop_entry.exec[0]).args(&desktop_entry.exec[1..]))
+    };
+    if let Err(why) = launched {
+        log::error!(
+            "Error running desktop entry {}: {}",
+            desktop_entry.desktop_id,
+            why
+        );
     }
 
     HandleResult::Close
 }
 
-#[init]
-pub fn init(config_dir: RString) -> State {
-    let config: Config = match fs::read_to_string(format!("{}/applications.ron", config_dir)) {
-        Ok(content) => ron::from_str(&content).unwrap_or_else(|why| {
-            eprintln!("Error parsing applications plugin config: {}", why);
-            Config::default()
-        }),
-        Err(why) => {
-            eprintln!("Error reading applications plugin config: {}", why);
-            Config::default()
+/// Reveal `file` in the file manager, or at least open its directory.
+fn show_file(file: &Path) {
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(file)))
+        .arg("string:")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if shown {
+        return;
+    }
+
+    let dir = file.parent().unwrap_or(file);
+    if let Err(why) = Spawn::new("xdg-open").arg(dir).spawn() {
+        log::error!("Error showing {}: {}", file.display(), why);
+    }
+}
+
+/// `file` as a `file://` URI, with every byte percent-encoded that is not allowed in a path or
+/// would split the dbus-send array, e.g. spaces, `%`, `#` and `,`.
+fn file_uri(file: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in file.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
         }
-    };
+    }
+    uri
+}
 
-    let entries = scrubber::scrubber(&config).unwrap_or_else(|why| {
-        eprintln!("Failed to load desktop entries: {}", why);
-        Vec::new()
+#[init]
+pub fn init(config_dir: RString) -> State {
+    let config = Watched::<Config>::load(&config_dir, "applications");
+    log::init("applications", &config.get().log);
+    let interval = Some(config.get().refresh_interval)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    let locale = Locale::from_env();
+    let entries = Refreshed::spawn("desktop entries", interval, move || {
+        let dirs = scrubber::desktop_dirs();
+        // The entries are translated, so a different locale needs different entries
+        let version = format!("{}:{}", env!("CARGO_PKG_VERSION"), scrubber::CACHE_FORMAT);
+        let key = match &locale {
+            Some(locale) => format!("{}:{}", version, locale),
+            None => version,
+        };
+        let entries = cache::cached("applications", &key, &dirs, || {
+            scrubber::scrubber(&dirs, locale.as_ref())
+        })?;
+        Ok::<_, Box<dyn std::error::Error>>(Registry::new(
+            entries.into_iter().map(NucleoEntry::new),
+            |entry| entry.id,
+        ))
     });
 
-    State { config, entries }
+    State {
+        config,
+        entries,
+        frecency: Frecency::load("applications"),
+    }
 }
 
 #[get_matches]
 pub fn get_matches(input: RString, state: &State) -> RVec<Match> {
-    let mut entries = fuzzy_match(&input, &state.entries);
-    entries.sort_by(|a, b| b.1.cmp(&a.1));
+    let config = state.config.get();
+    if let Some(matches) = report::unavailable("applications", &state.entries) {
+        return matches;
+    }
+    let actions = actions();
+    let (action, input) = actions.parse(&input);
+    let render = |text: &str| {
+        if config.highlight_matches {
+            highlight(input, text, &config.search.normalize)
+        } else {
+            text.to_string()
+        }
+    };
 
-    entries.truncate(state.config.max_entries);
-    entries
+    let desktop_entries = state.entries.get();
+    let entries = desktop_entries
+        .iter()
+        .filter(|entry| config.desktop_actions || !entry.desktop_entry.action);
+    let mut entries = if input.trim().is_empty() {
+        config
+            .empty_query
+            .list(entries, Some(&state.frecency), |entry| {
+                &entry.desktop_entry.desktop_id
+            })
+            .into_iter()
+            .map(|entry| (entry, 0))
+            .collect()
+    } else {
+        let entries = entries.map(|entry| Searched {
+            entry,
+            fields: &config.search_fields,
+        });
+        let mut entries = search_with(input, entries, &config.search)
+            .into_iter()
+            .map(|(searched, score)| (searched.entry, score))
+            .collect::<Vec<_>>();
+        state.frecency.rank(
+            &mut entries,
+            |entry| &entry.desktop_entry.desktop_id,
+            config.frecency_weight,
+        );
+        rank::sort(&mut entries, input, &config.search.tie_breaks, |entry| {
+            SortKeys {
+                text: &entry.desktop_entry.name,
+                source: desktop_entries.position(entry.id).unwrap_or(usize::MAX),
+                last_used: state.frecency.last_used(&entry.desktop_entry.desktop_id),
+            }
+        });
+        entries
+    };
+    entries.truncate(config.max_entries);
+    let matches = entries
         .into_iter()
         .map(|(entry, _)| Match {
-            title: entry.desktop_entry.name.clone().into(),
-            description: entry
-                .desktop_entry
-                .desc
-                .clone()
-                .map(|desc| desc.into())
+            title: render(&entry.desktop_entry.name).into(),
+            description: actions
+                .describe(
+                    action,
+                    entry
+                        .desktop_entry
+                        .description(&config.description)
+                        .as_deref()
+                        .map(render),
+                )
+                .map(RString::from)
                 .into(),
-            use_pango: false,
+            use_pango: config.highlight_matches,
             icon: ROption::RSome(entry.desktop_entry.icon.clone().into()),
-            id: ROption::RSome(entry.id),
+            id: ROption::RSome(actions.id(entry.id, action)),
         })
-        .collect()
+        .collect();
+    report::with_errors(
+        "applications",
+        [state.config.error(), state.entries.error()],
+        matches,
+    )
 }
 
 #[info]
@@ -134,3 +328,20 @@ pub fn info() -> PluginInfo {
         icon: "application-x-executable".into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri(Path::new("/usr/share/applications/firefox.desktop")),
+            "file:///usr/share/applications/firefox.desktop"
+        );
+        assert_eq!(
+            file_uri(Path::new("/home/me/My Apps/50%,ü#1.desktop")),
+            "file:///home/me/My%20Apps/50%25%2C%C3%BC%231.desktop"
+        );
+    }
+}
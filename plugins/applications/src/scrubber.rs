@@ -1,266 +1,487 @@
-use std::{collections::HashMap, env, ffi::OsStr, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::Config;
+use serde::{Deserialize, Serialize};
+
+use crate::desktop_file::{self, DesktopFile, FieldCodes, Locale};
+use utils::{ids::stable_id, log, Field, FULL_WEIGHT};
 
 pub struct NucleoEntry {
     pub desktop_entry: DesktopEntry,
     pub id: u64,
 }
 
-impl AsRef<str> for NucleoEntry {
-    fn as_ref(&self) -> &str {
-        self.desktop_entry.name.as_ref()
+impl NucleoEntry {
+    pub fn new(desktop_entry: DesktopEntry) -> Self {
+        Self {
+            id: stable_id(&desktop_entry.desktop_id),
+            desktop_entry,
+        }
+    }
+}
+
+/// A part of a desktop entry that applications can be searched by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SearchField {
+    /// The translated and the untranslated name
+    Name,
+    Keywords,
+    /// The kind of application, e.g. "Web Browser"
+    GenericName,
+    /// The menu categories, e.g. `Network` and `WebBrowser`
+    Categories,
+}
+
+pub const ALL_SEARCH_FIELDS: &[SearchField] = &[
+    SearchField::Name,
+    SearchField::Keywords,
+    SearchField::GenericName,
+    SearchField::Categories,
+];
+
+impl NucleoEntry {
+    /// The searched texts of `fields`, everything but the name counts less.
+    pub fn search_fields(&self, fields: &[SearchField]) -> Vec<Field<'_>> {
+        let entry = &self.desktop_entry;
+        let mut searched = Vec::new();
+        for field in fields {
+            match field {
+                SearchField::Name => {
+                    searched.push(Field::new("name", &entry.name, FULL_WEIGHT));
+                    if let Some(name) = &entry.untranslated_name {
+                        searched.push(Field::new("untranslated_name", name, FULL_WEIGHT));
+                    }
+                }
+                SearchField::Keywords if !entry.keywords.is_empty() => {
+                    searched.push(Field::new("keywords", entry.keywords.join(" "), 60));
+                }
+                SearchField::GenericName => {
+                    if let Some(generic_name) = &entry.generic_name {
+                        searched.push(Field::new("generic_name", generic_name, 70));
+                    }
+                }
+                SearchField::Categories if !entry.categories.is_empty() => {
+                    searched.push(Field::new("categories", entry.categories.join(" "), 40));
+                }
+                _ => {}
+            }
+        }
+        searched
     }
 }
 
-#[derive(Clone, Debug)]
+/// Version of the cached [`DesktopEntry`]s, bump it on every change to their fields or to which
+/// entries [`scrubber`] keeps, so caches written by an older build are rebuilt.
+pub const CACHE_FORMAT: u32 = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DesktopEntry {
-    pub exec: String,
+    /// Desktop file id, followed by `:<action>` for desktop actions
+    pub desktop_id: String,
+    /// The desktop file the entry was read from
+    pub file: PathBuf,
+    /// The program and its arguments, with the field codes expanded
+    pub exec: Vec<String>,
     pub path: Option<PathBuf>,
+    /// The name in the user's language
     pub name: String,
+    /// The untranslated name, if it differs from `name`
+    pub untranslated_name: Option<String>,
     pub keywords: Vec<String>,
+    /// The translated kind of application, e.g. "Web Browser"
+    pub generic_name: Option<String>,
+    pub categories: Vec<String>,
+    /// The translated tooltip, e.g. "Browse the World Wide Web"
+    pub comment: Option<String>,
+    /// The name of the application a desktop action belongs to
     pub desc: Option<String>,
     pub icon: String,
     pub term: bool,
-    pub offset: i64,
+    /// Whether this is a desktop action of another entry
+    pub action: bool,
 }
 
-const FIELD_CODE_LIST: &[&str] = &[
-    "%f", "%F", "%u", "%U", "%d", "%D", "%n", "%N", "%i", "%c", "%k", "%v", "%m",
-];
-
 impl DesktopEntry {
-    fn from_dir_entry(entry: &fs::DirEntry, config: &Config) -> Vec<Self> {
-        if entry.path().extension() == Some(OsStr::new("desktop")) {
-            let content = match fs::read_to_string(entry.path()) {
-                Ok(content) => content,
-                Err(_) => return Vec::new(),
+    /// The description shown below the name, desktop actions show the name of their application.
+    ///
+    /// Otherwise the first of `templates` whose `{name}`, `{generic}` and `{comment}`
+    /// placeholders all have a value is filled in.
+    pub fn description(&self, templates: &[String]) -> Option<String> {
+        if self.desc.is_some() {
+            return self.desc.clone();
+        }
+        let values = [
+            ("{name}", Some(&self.name)),
+            ("{generic}", self.generic_name.as_ref()),
+            ("{comment}", self.comment.as_ref()),
+        ];
+        templates.iter().find_map(|template| {
+            let mut description = template.clone();
+            for (placeholder, value) in values {
+                if template.contains(placeholder) {
+                    let value = value.filter(|value| !value.is_empty())?;
+                    description = description.replace(placeholder, value);
+                }
+            }
+            Some(description)
+        })
+    }
+
+    /// The application in the desktop file at `file` and its desktop actions.
+    ///
+    /// Hidden entries and other types than `Application`, like links, give no entries. Names
+    /// and keywords are translated to `locale`.
+    pub fn from_file(
+        file: &Path,
+        desktop_id: &str,
+        locale: Option<&Locale>,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let desktop_file = DesktopFile::parse(&fs::read_to_string(file)?)?;
+        let group = desktop_file
+            .group("Desktop Entry")
+            .ok_or("No [Desktop Entry] group")?;
+        let boolean = |key| {
+            group.boolean(key).unwrap_or_else(|why| {
+                log::warn!("{} in {}", why, file.display());
+                false
+            })
+        };
+        if group.raw("Type") != Some("Application") || boolean("NoDisplay") || boolean("Hidden") {
+            return Ok(Vec::new());
+        }
+
+        let name = group.localized("Name", locale).ok_or("No Name key")?;
+        let icon = group.string("Icon");
+        let exec = |exec: &str, icon: Option<&str>| {
+            let codes = FieldCodes {
+                icon,
+                name: &name,
+                file,
             };
+            desktop_file::exec_args(exec, &codes)
+        };
+        let entry = DesktopEntry {
+            desktop_id: desktop_id.to_string(),
+            file: file.to_path_buf(),
+            exec: exec(&group.string("Exec").ok_or("No Exec key")?, icon.as_deref())?,
+            path: group.string("Path").map(PathBuf::from),
+            name: name.clone(),
+            untranslated_name: group
+                .string("Name")
+                .filter(|untranslated| *untranslated != name),
+            keywords: group.localized_list("Keywords", locale).unwrap_or_default(),
+            generic_name: group.localized("GenericName", locale),
+            categories: group.strings("Categories").unwrap_or_default(),
+            comment: group.localized("Comment", locale),
+            desc: None,
+            icon: icon
+                .clone()
+                .unwrap_or_else(|| "application-x-executable".to_string()),
+            term: boolean("Terminal"),
+            action: false,
+        };
 
-            let lines = content.lines().collect::<Vec<_>>();
+        // Only the actions listed in `Actions` are shown, in that order
+        let mut entries = group
+            .strings("Actions")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|action| {
+                let group = desktop_file.group(&format!("Desktop Action {}", action))?;
+                let action_icon = group.string("Icon").or_else(|| icon.clone());
+                // Actions without Exec are only activated over D-Bus
+                let action_exec = match exec(&group.string("Exec")?, action_icon.as_deref()) {
+                    Ok(exec) => exec,
+                    Err(why) => {
+                        log::warn!("Skipping action {} of {}: {}", action, file.display(), why);
+                        return None;
+                    }
+                };
+                let action_name = group.localized("Name", locale)?;
+                Some(DesktopEntry {
+                    desktop_id: format!("{}:{}", desktop_id, action),
+                    exec: action_exec,
+                    untranslated_name: group
+                        .string("Name")
+                        .filter(|untranslated| *untranslated != action_name),
+                    name: action_name,
+                    keywords: Vec::new(),
+                    generic_name: None,
+                    categories: Vec::new(),
+                    comment: None,
+                    desc: Some(entry.name.clone()),
+                    icon: action_icon.unwrap_or_else(|| entry.icon.clone()),
+                    action: true,
+                    ..entry.clone()
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.push(entry);
+        Ok(entries)
+    }
+}
 
-            let sections = lines
-                .split_inclusive(|line| line.starts_with('['))
-                .collect::<Vec<_>>();
+/// Directories desktop files are read from, the user's own last since it overrides the others.
+pub fn desktop_dirs() -> Vec<PathBuf> {
+    // XDG compliancy is cool
+    let mut dirs = match env::var("XDG_DATA_DIRS") {
+        Ok(data_dirs) => data_dirs
+            .split(':')
+            .map(|dir| Path::new(dir).join("applications"))
+            .collect(),
+        Err(_) => vec![PathBuf::from("/usr/share/applications")],
+    };
+    match (env::var("XDG_DATA_HOME"), env::var("HOME")) {
+        (Ok(data_home), _) => dirs.push(Path::new(&data_home).join("applications")),
+        (_, Ok(home)) => dirs.push(Path::new(&home).join(".local/share/applications")),
+        _ => log::warn!("Unable to determine the home directory, skipping user desktop files"),
+    }
+    dirs
+}
 
-            let mut line = None;
-            let mut new_sections = Vec::new();
+pub fn scrubber(
+    dirs: &[PathBuf],
+    locale: Option<&Locale>,
+) -> Result<Vec<DesktopEntry>, Box<dyn std::error::Error>> {
+    let Some((user_dir, system_dirs)) = dirs.split_last() else {
+        return Err("No desktop file dirs given!".into());
+    };
 
-            for (i, section) in sections.iter().enumerate() {
-                if let Some(line) = line {
-                    let mut section = section.to_vec();
-                    section.insert(0, line);
+    // The vec for all the DirEntry objects, in increasing precedence since a desktop file
+    // overrides the ones with the same id read before it
+    let mut paths = Vec::new();
+    for dir in system_dirs.iter().rev() {
+        match fs::read_dir(dir) {
+            Ok(dir) => paths.extend(dir),
+            Err(why) => log::warn!("Error reading directory {}: {}", dir.display(), why),
+        }
+    }
+    // Make sure the list of paths isn't empty
+    if paths.is_empty() {
+        return Err("No valid desktop file dirs found!".into());
+    }
 
-                    // Only pop the last redundant entry if it isn't the last item
-                    if i < sections.len() - 1 {
-                        section.pop();
-                    }
-                    new_sections.push(section);
+    // Go through user directory desktop files last for overrides
+    match fs::read_dir(user_dir) {
+        Ok(dir) => paths.extend(dir),
+        Err(why) => log::warn!("Error reading directory {}: {}", user_dir.display(), why),
+    }
+
+    // Keyed by desktop id, so a hidden file also hides the entries of the ones it overrides
+    let files: HashMap<String, Vec<DesktopEntry>> = paths
+        .into_iter()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension() != Some(OsStr::new("desktop")) {
+                return None;
+            }
+            let desktop_id = path.file_name()?.to_string_lossy().into_owned();
+            match DesktopEntry::from_file(&path, &desktop_id, locale) {
+                Ok(entries) => Some((desktop_id, entries)),
+                Err(why) => {
+                    log::warn!("Skipping {}: {}", path.display(), why);
+                    None
                 }
-                line = Some(section.last().unwrap_or(&""));
             }
+        })
+        .collect();
 
-            let mut ret = Vec::new();
+    // Sorted, so the order and the cache do not change between runs
+    let mut entries = files.into_values().flatten().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.desktop_id.cmp(&b.desktop_id));
+    Ok(entries)
+}
 
-            let entry = match new_sections.iter().find_map(|section| {
-                if section[0].starts_with("[Desktop Entry]") {
-                    let mut map = HashMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    for line in section.iter().skip(1) {
-                        if let Some((key, val)) = line.split_once('=') {
-                            map.insert(key, val);
-                        }
-                    }
+    fn fixture(
+        name: &str,
+        locale: Option<&str>,
+    ) -> Result<Vec<DesktopEntry>, Box<dyn std::error::Error>> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        DesktopEntry::from_file(&path, name, locale.and_then(Locale::parse).as_ref())
+    }
 
-                    if map.get("Type")? == &"Application"
-                        && match map.get("NoDisplay") {
-                            Some(no_display) => !no_display.parse::<bool>().unwrap_or(true),
-                            None => true,
-                        }
-                    {
-                        Some(DesktopEntry {
-                            exec: {
-                                let mut exec = map.get("Exec")?.to_string();
-
-                                for field_code in FIELD_CODE_LIST {
-                                    exec = exec.replace(field_code, "");
-                                }
-                                exec
-                            },
-                            path: map.get("Path").map(PathBuf::from),
-                            name: map.get("Name")?.to_string(),
-                            keywords: map
-                                .get("Keywords")
-                                .map(|keywords| {
-                                    keywords
-                                        .split(';')
-                                        .map(|s| s.to_owned())
-                                        .collect::<Vec<_>>()
-                                })
-                                .unwrap_or_default(),
-                            desc: None,
-                            icon: map
-                                .get("Icon")
-                                .unwrap_or(&"application-x-executable")
-                                .to_string(),
-                            term: map
-                                .get("Terminal")
-                                .map(|val| val.to_lowercase() == "true")
-                                .unwrap_or(false),
-                            offset: 0,
-                        })
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }) {
-                Some(entry) => entry,
-                None => return Vec::new(),
-            };
+    fn from_fixture(name: &str) -> Result<Vec<DesktopEntry>, Box<dyn std::error::Error>> {
+        fixture(name, None)
+    }
 
-            if config.desktop_actions {
-                for (i, section) in new_sections.iter().enumerate() {
-                    let mut map = HashMap::new();
+    #[test]
+    fn test_from_file() {
+        let firefox = from_fixture("firefox.desktop").unwrap();
+        let names = firefox
+            .iter()
+            .map(|entry| (entry.desktop_id.as_str(), entry.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("firefox.desktop:new-window", "New Window"),
+                ("firefox.desktop:new-private-window", "New Private Window"),
+                ("firefox.desktop", "Firefox"),
+            ]
+        );
+        assert_eq!(firefox[0].desc.as_deref(), Some("Firefox"));
+        assert_eq!(firefox[0].icon, "firefox");
+        assert_eq!(firefox[0].exec, ["firefox", "--new-window"]);
+        assert_eq!(firefox[2].exec, ["env", "MOZ_ENABLE_WAYLAND=1", "firefox"]);
+        assert_eq!(
+            firefox[2].keywords,
+            ["Internet", "WWW", "Browser", "Web", "Explorer"]
+        );
 
-                    for line in section.iter().skip(1) {
-                        if let Some((key, val)) = line.split_once('=') {
-                            map.insert(key, val);
-                        }
-                    }
+        // Shipped by Vim, with many translations and comments between the keys
+        let vim = from_fixture("vim.desktop").unwrap();
+        assert_eq!(vim.len(), 1);
+        assert_eq!((vim[0].name.as_str(), vim[0].term), ("Vim", true));
+        assert_eq!(vim[0].keywords, ["Text", "editor"]);
 
-                    if section[0].starts_with("[Desktop Action") {
-                        ret.push(DesktopEntry {
-                            exec: match map.get("Exec") {
-                                Some(exec) => {
-                                    let mut exec = exec.to_string();
-
-                                    for field_code in FIELD_CODE_LIST {
-                                        exec = exec.replace(field_code, "");
-                                    }
-                                    exec
-                                }
-                                None => continue,
-                            },
-                            path: entry.path.clone(),
-                            name: match map.get("Name") {
-                                Some(name) => name.to_string(),
-                                None => continue,
-                            },
-                            keywords: map
-                                .get("Keywords")
-                                .map(|keywords| {
-                                    keywords
-                                        .split(';')
-                                        .map(|s| s.to_owned())
-                                        .collect::<Vec<_>>()
-                                })
-                                .unwrap_or_default(),
-                            desc: Some(entry.name.clone()),
-                            icon: entry.icon.clone(),
-                            term: map
-                                .get("Terminal")
-                                .map(|val| val.to_lowercase() == "true")
-                                .unwrap_or(false),
-                            offset: i as i64,
-                        })
-                    }
-                }
-            }
+        let firefox = fixture("firefox.desktop", Some("de_DE.UTF-8")).unwrap();
+        assert_eq!(firefox[0].name, "Neues Fenster");
+        assert_eq!(firefox[0].untranslated_name.as_deref(), Some("New Window"));
+        assert_eq!(firefox[2].name, "Firefox");
+        assert_eq!(firefox[2].untranslated_name, None);
+        assert!(firefox[2].keywords.contains(&"surfen".to_string()));
+        assert_eq!(firefox[2].generic_name.as_deref(), Some("Webbrowser"));
+        assert_eq!(firefox[2].categories, ["Network", "WebBrowser"]);
 
-            ret.push(entry);
-            ret
-        } else {
-            Vec::new()
-        }
+        let vim = fixture("vim.desktop", Some("zh_CN.UTF-8")).unwrap();
+        assert_eq!(vim[0].keywords, ["Text", "editor", "文本", "编辑器"]);
+
+        let vendor = from_fixture("vendor-group.desktop").unwrap();
+        assert_eq!(vendor[0].name, "Vendor Group");
+        assert_eq!(vendor[0].exec, ["vendor", "--run"]);
+
+        let spaces = from_fixture("spaces.desktop").unwrap();
+        assert_eq!(spaces[0].name, "Spaced Out ");
+        assert!(spaces[0].term);
+
+        // An invalid boolean counts as false
+        assert!(!from_fixture("booleans.desktop").unwrap()[0].term);
+
+        assert!(from_fixture("hidden.desktop").unwrap().is_empty());
+        assert!(from_fixture("link.desktop").unwrap().is_empty());
+        assert!(from_fixture("invalid-line.desktop").is_err());
     }
-}
 
-pub fn scrubber(config: &Config) -> Result<Vec<NucleoEntry>, Box<dyn std::error::Error>> {
-    // Create iterator over all the files in the XDG_DATA_DIRS
-    // XDG compliancy is cool
-    let user_path = match env::var("XDG_DATA_HOME") {
-        Ok(data_home) => {
-            format!("{}/applications/", data_home)
+    #[test]
+    fn test_scrubber() {
+        let dir =
+            std::env::temp_dir().join(format!("anyrun-plugins-scrubber-{}", std::process::id()));
+        let dirs = ["preferred", "fallback", "user"].map(|name| dir.join(name));
+        for dir in &dirs {
+            fs::create_dir_all(dir).unwrap();
         }
-        Err(_) => {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let desktop_file = |name: &str, exec: &str, extra: &str| {
             format!(
-                "{}/.local/share/applications/",
-                env::var("HOME").expect("Unable to determine home directory!")
+                "[Desktop Entry]\nType=Application\nName={}\nExec={}\n{}",
+                name, exec, extra
             )
-        }
-    };
+        };
+        fs::copy(fixtures.join("vim.desktop"), dirs[1].join("vim.desktop")).unwrap();
+        fs::copy(
+            fixtures.join("firefox.desktop"),
+            dirs[1].join("firefox.desktop"),
+        )
+        .unwrap();
+        let write =
+            |dir: &Path, id: &str, content: String| fs::write(dir.join(id), content).unwrap();
+        write(&dirs[0], "vim.desktop", desktop_file("Vim", "nvim", ""));
+        write(&dirs[1], "htop.desktop", desktop_file("Htop", "htop", ""));
+        // Same name and icon as the one it overrides, but a different id
+        write(
+            &dirs[2],
+            "my-htop.desktop",
+            desktop_file("Htop", "htop -t", ""),
+        );
+        write(
+            &dirs[2],
+            "firefox.desktop",
+            desktop_file("Firefox", "firefox", "Hidden=true"),
+        );
 
-    let mut entries: HashMap<String, DesktopEntry> = match env::var("XDG_DATA_DIRS") {
-        Ok(data_dirs) => {
-            // The vec for all the DirEntry objects
-            let mut paths = Vec::new();
-            // Parse the XDG_DATA_DIRS variable and list files of all the paths
-            for dir in data_dirs.split(':') {
-                match fs::read_dir(format!("{}/applications/", dir)) {
-                    Ok(dir) => {
-                        paths.extend(dir);
-                    }
-                    Err(why) => {
-                        eprintln!("Error reading directory {}: {}", dir, why);
-                    }
-                }
-            }
-            // Make sure the list of paths isn't empty
-            if paths.is_empty() {
-                return Err("No valid desktop file dirs found!".into());
-            }
+        let entries = scrubber(&dirs, None).unwrap();
+        let entries = entries
+            .iter()
+            .map(|entry| (entry.desktop_id.as_str(), entry.exec.join(" ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("htop.desktop", "htop".to_string()),
+                ("my-htop.desktop", "htop -t".to_string()),
+                ("vim.desktop", "nvim".to_string()),
+            ]
+        );
 
-            // Return it
-            paths
-        }
-        Err(_) => fs::read_dir("/usr/share/applications")?.collect(),
+        fs::remove_dir_all(&dir).unwrap();
     }
-    .into_iter()
-    .filter_map(|entry| {
-        let entry = match entry {
-            Ok(entry) => entry,
-            Err(_why) => return None,
-        };
-        let entries = DesktopEntry::from_dir_entry(&entry, config);
-        Some(
-            entries
-                .into_iter()
-                .map(|entry| (format!("{}{}", entry.name, entry.icon), entry)),
-        )
-    })
-    .flatten()
-    .collect();
-
-    // Go through user directory desktop files for overrides
-    match fs::read_dir(&user_path) {
-        Ok(dir_entries) => entries.extend(
-            dir_entries
-                .into_iter()
-                .filter_map(|entry| {
-                    let entry = match entry {
-                        Ok(entry) => entry,
-                        Err(_why) => return None,
-                    };
-                    let entries = DesktopEntry::from_dir_entry(&entry, config);
-                    Some(
-                        entries
-                            .into_iter()
-                            .map(|entry| (format!("{}{}", entry.name, entry.icon), entry)),
-                    )
-                })
-                .flatten(),
-        ),
-        Err(why) => eprintln!("Error reading directory {}: {}", user_path, why),
+
+    #[test]
+    fn test_description() {
+        let firefox = from_fixture("firefox.desktop").unwrap();
+        let vim = fixture("vim.desktop", Some("de_DE")).unwrap();
+        let templates = ["{generic} — {comment}".to_string(), "{comment}".to_string()];
+
+        assert_eq!(
+            firefox[2].description(&templates).as_deref(),
+            Some("Web Browser — Browse the World Wide Web")
+        );
+        assert_eq!(
+            vim[0].description(&templates).as_deref(),
+            Some("Texteditor — Textdateien bearbeiten")
+        );
+        // Only the comment is known
+        let spaces = from_fixture("escapes.desktop").unwrap();
+        assert_eq!(
+            spaces[0].description(&templates).as_deref(),
+            Some("Two\nlines\tand a \\ backslash")
+        );
+        assert_eq!(
+            from_fixture("vendor-group.desktop").unwrap()[0].description(&templates),
+            None
+        );
+        // Desktop actions keep the name of their application
+        assert_eq!(
+            firefox[0].description(&templates).as_deref(),
+            Some("Firefox")
+        );
+        assert_eq!(firefox[2].description(&[]), None);
     }
 
-    Ok(entries
-        .into_iter()
-        .enumerate()
-        .map(|(i, (_, entry))| NucleoEntry {
-            desktop_entry: entry,
-            id: i as u64,
-        })
-        .collect())
+    #[test]
+    fn test_search_fields() {
+        let firefox = fixture("firefox.desktop", Some("de_DE")).unwrap();
+        let entry = NucleoEntry::new(firefox[2].clone());
+        let searched = |fields: &[SearchField]| {
+            entry
+                .search_fields(fields)
+                .into_iter()
+                .map(|field| (field.name, field.text.into_owned()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            searched(&[SearchField::Name]),
+            [("name", "Firefox".to_string())]
+        );
+        assert_eq!(
+            searched(&[SearchField::GenericName, SearchField::Categories]),
+            [
+                ("generic_name", "Webbrowser".to_string()),
+                ("categories", "Network WebBrowser".to_string()),
+            ]
+        );
+        assert_eq!(searched(ALL_SEARCH_FIELDS).len(), 4);
+    }
 }
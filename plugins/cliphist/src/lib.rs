@@ -1,17 +1,49 @@
 use abi_stable::std_types::{ROption, RString, RVec};
 use anyrun_plugin::*;
 use serde::Deserialize;
-use std::fs;
-use std::io::Write;
+use std::io::{self, Write};
 use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
 
-use utils::fuzzy_match;
+use utils::{
+    actions::Actions,
+    config::Watched,
+    highlight,
+    ids::{stable_id, Registry},
+    log,
+    rank::{self, EmptyQuery, SortKeys},
+    refresh::Refreshed,
+    report,
+    router::Router,
+    search_with,
+    spawn::Spawn,
+    Field, SearchOptions, Searchable, FULL_WEIGHT,
+};
 
 #[derive(Deserialize)]
+#[serde(default)]
 struct Config {
     max_entries: usize,
     cliphist_path: String,
+    /// Seconds between reloads of the history, 0 only reloads after copying. Read at startup.
+    refresh_interval: u64,
     prefix: String,
+    /// Other prefixes that work like `prefix`
+    aliases: Vec<String>,
+    /// Whether the history is only searched when the query starts with a prefix
+    require_prefix: bool,
+    /// Sub-command after the prefix that only searches images
+    images_command: String,
+    /// Shell command run after copying an entry with `!paste`
+    paste_command: String,
+    highlight_matches: bool,
+    /// What is listed when nothing but the prefix is typed
+    empty_query: EmptyQuery,
+    /// Weak matches left out, tie-breaks, transliteration and unicode folding
+    search: SearchOptions,
+    /// Log level and file, read at startup
+    log: log::Config,
 }
 
 impl Default for Config {
@@ -19,53 +51,132 @@ impl Default for Config {
         Self {
             max_entries: 10,
             cliphist_path: "cliphist".into(),
+            refresh_interval: 5,
             prefix: ".".into(),
+            aliases: Vec::new(),
+            require_prefix: true,
+            images_command: "img".into(),
+            paste_command: "sleep 0.2 && wtype -M ctrl -k v -m ctrl".into(),
+            highlight_matches: false,
+            empty_query: EmptyQuery::Source,
+            search: SearchOptions::default(),
+            log: log::Config::default(),
         }
     }
 }
 
+impl Config {
+    fn router(&self) -> Router<Filter> {
+        Router::new(
+            std::iter::once(&self.prefix).chain(&self.aliases),
+            self.require_prefix,
+            Filter::All,
+        )
+        .command(&self.images_command, Filter::Images)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Filter {
+    All,
+    Images,
+}
+
+/// What happens to the selected entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Copy,
+    Paste,
+    Delete,
+}
+
+fn actions() -> Actions<Action> {
+    Actions::new(Action::Copy)
+        .action("paste", "Paste", Action::Paste)
+        .action("delete", "Delete", Action::Delete)
+}
+
 struct CliphistItem {
-    id: usize,
+    id: u64,
     cliphist_id: String,
     content: String,
 }
 
-impl AsRef<str> for CliphistItem {
-    fn as_ref(&self) -> &str {
-        &self.content
+impl CliphistItem {
+    fn is_image(&self) -> bool {
+        // cliphist lists images as e.g. `[[ binary data 12 KiB png 100x100 ]]`
+        let Some(preview) = self
+            .content
+            .strip_prefix("[[ binary data")
+            .and_then(|preview| preview.strip_suffix("]]"))
+        else {
+            return false;
+        };
+        preview
+            .split_whitespace()
+            .last()
+            .and_then(|size| size.split_once('x'))
+            .is_some_and(|(width, height)| {
+                width.parse::<u32>().is_ok() && height.parse::<u32>().is_ok()
+            })
+    }
+}
+
+impl Searchable for CliphistItem {
+    fn fields(&self) -> Vec<Field<'_>> {
+        vec![Field::new("content", &self.content, FULL_WEIGHT)]
     }
 }
 
 struct State {
-    config: Config,
-    history: Vec<CliphistItem>,
+    config: Arc<Watched<Config>>,
+    history: Refreshed<Registry<CliphistItem>>,
 }
 
 #[init]
 fn init(config_dir: RString) -> State {
-    let config = match fs::read_to_string(format!("{}/cliphist.ron", config_dir)) {
-        Ok(content) => ron::from_str(&content).unwrap_or_default(),
-        Err(_) => Config::default(),
-    };
+    let config = Arc::new(Watched::<Config>::load(&config_dir, "cliphist"));
+    log::init("cliphist", &config.get().log);
 
-    let list_child = Command::new(&config.cliphist_path)
-        .args(["list"])
-        .output()
-        .expect("Failed to execute cliphist list command");
-    let list = String::from_utf8_lossy(&list_child.stdout).into_owned();
+    let interval = Some(config.get().refresh_interval)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    let loader_config = config.clone();
+    let history = Refreshed::spawn("cliphist history", interval, move || {
+        load_history(&loader_config.get().cliphist_path)
+    });
+
+    State { config, history }
+}
+
+fn load_history(cliphist_path: &str) -> io::Result<Registry<CliphistItem>> {
+    let list_child = match Command::new(cliphist_path).args(["list"]).output() {
+        Ok(output) => output,
+        Err(why) if why.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                why.kind(),
+                format!("cliphist not found at `{}`", cliphist_path),
+            ))
+        }
+        Err(why) => return Err(why),
+    };
+    if !list_child.status.success() {
+        return Err(io::Error::other(format!(
+            "cliphist list failed: {}",
+            String::from_utf8_lossy(&list_child.stderr).trim()
+        )));
+    }
+    let list = String::from_utf8_lossy(&list_child.stdout);
 
     let history = list
         .split('\n')
         .filter_map(|l| l.split_once('\t'))
-        .enumerate()
-        .map(|(id, (a, b))| CliphistItem {
-            id,
+        .map(|(a, b)| CliphistItem {
+            id: stable_id(a),
             cliphist_id: a.to_string(),
             content: b.to_string(),
-        })
-        .collect::<Vec<_>>();
-
-    State { config, history }
+        });
+    Ok(Registry::new(history, |item| item.id))
 }
 
 #[info]
@@ -78,67 +189,138 @@ fn info() -> PluginInfo {
 
 #[get_matches]
 fn get_matches(input: RString, state: &State) -> RVec<Match> {
-    let input = if let Some(input) = input.strip_prefix(&state.config.prefix) {
-        input.trim()
-    } else {
+    let config = state.config.get();
+    let Some(route) = config.router().route(&input) else {
         return RVec::new();
     };
+    if let Some(matches) = report::unavailable("cliphist", &state.history) {
+        return matches;
+    }
+    let actions = actions();
+    let (action, input) = actions.parse(route.query);
 
-    let mut entries = fuzzy_match(input, &state.history);
-    entries.sort_by(|a, b| b.1.cmp(&a.1));
-    entries.truncate(state.config.max_entries);
-    entries
+    let history = state.history.get();
+    let items = history.iter().filter(|item| match route.command {
+        Filter::All => true,
+        Filter::Images => item.is_image(),
+    });
+    // cliphist lists the newest entries first, so the source order is the recency
+    let mut entries = if input.is_empty() {
+        config
+            .empty_query
+            .list(items, None, |item| &item.content)
+            .into_iter()
+            .map(|item| (item, 0))
+            .collect()
+    } else {
+        let mut entries = search_with(input, items, &config.search);
+        rank::sort(&mut entries, input, &config.search.tie_breaks, |item| {
+            SortKeys {
+                text: &item.content,
+                source: history.position(item.id).unwrap_or(usize::MAX),
+                last_used: None,
+            }
+        });
+        entries
+    };
+    entries.truncate(config.max_entries);
+    let matches = entries
         .into_iter()
         .map(|(item, _)| {
-            let title = item.content.clone();
+            let title = if config.highlight_matches {
+                highlight(input, &item.content, &config.search.normalize)
+            } else {
+                item.content.clone()
+            };
             Match {
                 title: title.into(),
-                description: ROption::RNone,
-                use_pango: false,
+                description: actions.describe(action, None).map(RString::from).into(),
+                use_pango: config.highlight_matches,
                 icon: ROption::RNone,
-                id: ROption::RSome(item.id as u64),
+                id: ROption::RSome(actions.id(item.id, action)),
             }
         })
-        .collect()
+        .collect();
+    report::with_errors(
+        "cliphist",
+        [state.config.error(), state.history.error()],
+        matches,
+    )
+}
+
+/// Decode the entry with the cliphist id line `id` and hand it to a detached `wl-copy`, which
+/// keeps serving the clipboard after anyrun exits.
+fn copy(cliphist_path: &str, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut decode = Command::new(cliphist_path)
+        .arg("decode")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = decode.stdin.take() {
+        stdin.write_all(id.as_bytes())?;
+    }
+    let decoded = decode.wait_with_output()?;
+    if !decoded.status.success() {
+        return Err(format!("`{} decode` failed with {}", cliphist_path, decoded.status).into());
+    }
+
+    Spawn::new("wl-copy").input(decoded.stdout).spawn()?;
+    Ok(())
 }
 
 #[handler]
 fn handler(selection: Match, state: &State) -> HandleResult {
-    let id = state
-        .history
-        .iter()
-        .find_map(|item| {
-            if item.id as u64 == selection.id.unwrap() {
-                Some(item.cliphist_id.clone())
-            } else {
-                None
+    if report::is_report(&selection) {
+        return HandleResult::Close;
+    }
+    let history = state.history.get();
+    let (id, action) =
+        match actions().resolve(selection.id.into_option(), |id| history.get(Some(id)).ok()) {
+            Ok((item, action)) => (format!("{}\t ", item.cliphist_id), action),
+            Err(why) => {
+                log::error!("Error copying cliphist entry: {}", why);
+                return HandleResult::Close;
             }
-        })
-        .map(|id| format!("{}\t ", id))
-        .unwrap();
+        };
+    let config = state.config.get();
 
-    let mut decode_child = Command::new(&state.config.cliphist_path)
-        .args(["decode"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to decode cliphist entry");
-
-    let mut decode_stdin = decode_child.stdin.take().expect("failed to get stdin");
-    std::thread::spawn(move || {
-        decode_stdin
-            .write_all(id.as_bytes())
-            .expect("failed to write to stdin")
-    });
+    if action == Action::Delete {
+        let deleted = Command::new(&config.cliphist_path)
+            .arg("delete")
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(id.as_bytes())?;
+                }
+                child.wait_with_output()
+            });
+        match deleted {
+            Ok(output) if !output.status.success() => log::error!(
+                "Error deleting cliphist entry: cliphist delete failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(_) => {}
+            Err(why) => log::error!("Error deleting cliphist entry: {}", why),
+        }
+        state.history.refresh();
+        return HandleResult::Close;
+    }
 
-    let decode_out = decode_child
-        .stdout
-        .expect("Failed to spawn cliphist decode");
+    if let Err(why) = copy(&config.cliphist_path, &id) {
+        log::error!("Error copying cliphist entry: {}", why);
+        return HandleResult::Close;
+    }
+    // Copying moves the entry to the top of the history
+    state.history.refresh();
 
-    let _copy_child = Command::new("wl-copy")
-        .stdin(Stdio::from(decode_out))
-        .spawn()
-        .expect("Failed to spawn wl-copy");
+    // The paste has to wait for anyrun to close and the previous window to get the focus back
+    if action == Action::Paste {
+        if let Err(why) = Spawn::shell(&config.paste_command).spawn() {
+            log::error!("Error pasting cliphist entry: {}", why);
+        }
+    }
 
     HandleResult::Close
 }
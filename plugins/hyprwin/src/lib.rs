@@ -1,16 +1,26 @@
 use abi_stable::std_types::{ROption, RString, RVec};
 use anyrun_plugin::*;
 use core::ops::Deref;
-use hyprland::data::{Client, Clients};
-use hyprland::shared::HyprData;
-use serde::Deserialize;
-use std::fs;
-use utils::fuzzy_match;
+use hyprland::data::{Client, Workspace};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{env, error::Error, process::Command as Process, time::Duration};
+use utils::{
+    actions::Actions,
+    config::Watched,
+    frecency::{self, Frecency},
+    highlight,
+    ids::{stable_id, Registry},
+    log,
+    rank::{self, EmptyQuery, SortKeys},
+    refresh::Refreshed,
+    report,
+    router::Router,
+    search_with, Field, SearchOptions, Searchable, FULL_WEIGHT,
+};
 
 #[derive(Debug, Clone)]
 struct ClientId {
     client: Client,
-    search: String,
     id: u64,
 }
 
@@ -21,51 +31,224 @@ impl Deref for ClientId {
     }
 }
 
-impl AsRef<str> for ClientId {
-    fn as_ref(&self) -> &str {
-        &self.search
+impl Searchable for ClientId {
+    fn fields(&self) -> Vec<Field<'_>> {
+        vec![
+            Field::new("class", &self.class, FULL_WEIGHT),
+            Field::new("title", &self.title, 80),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct WorkspaceId {
+    workspace: Workspace,
+    id: u64,
+}
+
+impl Deref for WorkspaceId {
+    type Target = Workspace;
+    fn deref(&self) -> &Self::Target {
+        &self.workspace
+    }
+}
+
+impl Searchable for WorkspaceId {
+    fn fields(&self) -> Vec<Field<'_>> {
+        vec![
+            Field::new("name", &self.name, FULL_WEIGHT),
+            Field::new("last_window", &self.last_window_title, 60),
+        ]
     }
 }
 
 #[derive(Deserialize)]
+#[serde(default)]
 struct Config {
     max_entries: usize,
+    /// Seconds between reloads of the windows and workspaces, 0 only reloads after focusing.
+    /// Read at startup.
+    refresh_interval: u64,
     prefix: String,
+    /// Other prefixes that work like `prefix`
+    aliases: Vec<String>,
+    /// Whether windows are only searched when the query starts with a prefix
+    require_prefix: bool,
+    /// Sub-command after the prefix that searches workspaces instead of windows
+    workspaces_command: String,
+    highlight_matches: bool,
+    /// Which windows are listed when nothing but the prefix is typed
+    empty_query: EmptyQuery,
+    /// Weak matches left out, tie-breaks, transliteration and unicode folding
+    search: SearchOptions,
+    frecency_weight: u32,
+    /// Log level and file, read at startup
+    log: log::Config,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             max_entries: 5,
+            refresh_interval: 2,
             prefix: "/".into(),
+            aliases: Vec::new(),
+            require_prefix: true,
+            workspaces_command: "ws".into(),
+            highlight_matches: false,
+            empty_query: EmptyQuery::Source,
+            search: SearchOptions::default(),
+            frecency_weight: frecency::DEFAULT_WEIGHT,
+            log: log::Config::default(),
         }
     }
 }
 
+impl Config {
+    fn router(&self) -> Router<Command> {
+        Router::new(
+            std::iter::once(&self.prefix).chain(&self.aliases),
+            self.require_prefix,
+            Command::Windows,
+        )
+        .command(&self.workspaces_command, Command::Workspaces)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Windows,
+    Workspaces,
+}
+
+/// What happens to the selected window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Focus,
+    Close,
+    Move,
+}
+
+fn actions() -> Actions<Action> {
+    Actions::new(Action::Focus)
+        .action("close", "Close", Action::Close)
+        .action("move", "Move here", Action::Move)
+}
+
+/// The field of a `hyprctl -j clients` window the hyprland crate does not read.
+#[derive(Deserialize)]
+struct FocusHistory {
+    /// 0 for the focused window, counting up for the ones focused before it. Older Hyprland
+    /// versions leave it out, so their windows keep hyprctl's order.
+    #[serde(rename = "focusHistoryID", default)]
+    focus_history_id: i64,
+}
+
+/// The output of `hyprctl` with `args`.
+///
+/// Everything goes through hyprctl, the socket functions of the hyprland crate panic when
+/// Hyprland is not running.
+fn hyprctl(args: &[&str]) -> Result<Vec<u8>, Box<dyn Error>> {
+    // hyprctl would fall back to any running instance, not the one anyrun runs in
+    if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_none() {
+        return Err("Hyprland is not running, HYPRLAND_INSTANCE_SIGNATURE is not set".into());
+    }
+    let output = Process::new("hyprctl").args(args).output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "hyprctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+/// The `hyprctl -j <command>` data, e.g. the workspaces.
+fn data<T: DeserializeOwned>(command: &str) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&hyprctl(&["-j", command])?)?)
+}
+
+/// Run the Hyprland dispatcher `dispatcher` with `arg`, e.g. `focuswindow address:0x1234`.
+fn dispatch(dispatcher: &str, arg: &str) -> Result<(), Box<dyn Error>> {
+    let output = hyprctl(&["dispatch", dispatcher, arg])?;
+    // Failed dispatches still exit successfully, only their reply tells
+    match String::from_utf8_lossy(&output).trim() {
+        "ok" => Ok(()),
+        reply => Err(reply.to_string().into()),
+    }
+}
+
+/// The windows, the most recently focused first.
+fn clients() -> Result<Vec<Client>, Box<dyn Error>> {
+    Ok(by_focus_history(&hyprctl(&["-j", "clients"])?)?)
+}
+
+/// The windows in the output of `hyprctl -j clients`, the most recently focused first.
+fn by_focus_history(json: &[u8]) -> serde_json::Result<Vec<Client>> {
+    // Read twice since `Client` cannot be flattened into another struct, it has an i128 field
+    let clients: Vec<Client> = serde_json::from_slice(json)?;
+    let history: Vec<FocusHistory> = serde_json::from_slice(json)?;
+    let mut clients = history
+        .into_iter()
+        .map(|history| history.focus_history_id)
+        .zip(clients)
+        .collect::<Vec<_>>();
+    clients.sort_by_key(|(focus_history_id, _)| *focus_history_id);
+    Ok(clients.into_iter().map(|(_, client)| client).collect())
+}
+
+#[derive(Default)]
+struct Hyprland {
+    clients: Registry<ClientId>,
+    workspaces: Registry<WorkspaceId>,
+}
+
+impl Hyprland {
+    fn load() -> Result<Self, Box<dyn Error>> {
+        let clients = clients()?
+            .into_iter()
+            .filter(|client| !(client.title.is_empty() && client.class.is_empty()))
+            .map(|client| ClientId {
+                id: stable_id(&client.address.to_string()),
+                client,
+            });
+
+        let workspaces = data::<Vec<Workspace>>("workspaces")?
+            .into_iter()
+            .map(|workspace| WorkspaceId {
+                id: stable_id(&format!("workspace:{}", workspace.id)),
+                workspace,
+            });
+
+        Ok(Self {
+            clients: Registry::new(clients, |client| client.id),
+            workspaces: Registry::new(workspaces, |workspace| workspace.id),
+        })
+    }
+}
+
 struct State {
-    clients: Vec<ClientId>,
-    config: Config,
+    hyprland: Refreshed<Hyprland>,
+    config: Watched<Config>,
+    frecency: Frecency,
 }
 
 #[init]
 fn init(config_dir: RString) -> State {
-    let config = match fs::read_to_string(format!("{}/hyprwin.ron", config_dir)) {
-        Ok(content) => ron::from_str(&content).unwrap_or_default(),
-        Err(_) => Config::default(),
-    };
+    let config = Watched::<Config>::load(&config_dir, "hyprwin");
+    log::init("hyprwin", &config.get().log);
+    let interval = Some(config.get().refresh_interval)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
 
     State {
-        clients: Clients::get()
-            .expect("Failed to get clients")
-            .filter(|client| !(client.title.is_empty() && client.class.is_empty()))
-            .enumerate()
-            .map(|(idx, client)| ClientId {
-                id: idx as u64,
-                search: format!("{}: {}", client.class, client.title),
-                client,
-            })
-            .collect(),
+        hyprland: Refreshed::spawn("hyprland windows", interval, || {
+            Hyprland::load().map_err(|why| format!("Unable to reach Hyprland: {}", why))
+        }),
         config,
+        frecency: Frecency::load("hyprwin"),
     }
 }
 
@@ -79,52 +262,208 @@ fn info() -> PluginInfo {
 
 #[get_matches]
 fn get_matches(input: RString, state: &State) -> RVec<Match> {
-    let input = if let Some(input) = input.strip_prefix(&state.config.prefix) {
-        input.trim()
-    } else {
+    let config = state.config.get();
+    let Some(route) = config.router().route(&input) else {
         return RVec::new();
     };
+    if let Some(matches) = report::unavailable("hyprwin", &state.hyprland) {
+        return matches;
+    }
+    let actions = actions();
+    let (action, input) = match route.command {
+        Command::Windows => actions.parse(route.query),
+        Command::Workspaces => (Action::Focus, route.query),
+    };
 
-    let mut entries = fuzzy_match(input, &state.clients);
-    entries.sort_by(|a, b| b.1.cmp(&a.1));
-    entries.truncate(state.config.max_entries);
-    entries
-        .into_iter()
-        .map(|(client, _)| Match {
-            title: client.class.clone().into(),
-            icon: ROption::RSome(icon_from_class(&client.class).into()),
-            use_pango: false,
-            description: ROption::RSome(client.title.clone().into()),
-            id: ROption::RSome(client.id),
-        })
-        .collect::<Vec<Match>>()
-        .into()
+    let render = |text: &str| {
+        if config.highlight_matches {
+            highlight(input, text, &config.search.normalize)
+        } else {
+            text.to_string()
+        }
+    };
+    let hyprland = state.hyprland.get();
+
+    let matches = match route.command {
+        // Window addresses change between sessions, the class is what users come back to
+        Command::Windows if input.is_empty() => config
+            .empty_query
+            .list(&hyprland.clients, Some(&state.frecency), |client| {
+                &client.class
+            })
+            .into_iter()
+            .take(config.max_entries)
+            .map(|client| window_match(client, action, &actions, render, config.highlight_matches))
+            .collect(),
+        Command::Windows => {
+            let mut entries = search_with(input, &hyprland.clients, &config.search);
+            state
+                .frecency
+                .rank(&mut entries, |client| &client.class, config.frecency_weight);
+            rank::sort(&mut entries, input, &config.search.tie_breaks, |client| {
+                SortKeys {
+                    text: &client.class,
+                    source: hyprland.clients.position(client.id).unwrap_or(usize::MAX),
+                    last_used: state.frecency.last_used(&client.class),
+                }
+            });
+            entries.truncate(config.max_entries);
+            entries
+                .into_iter()
+                .map(|(client, _)| {
+                    window_match(client, action, &actions, render, config.highlight_matches)
+                })
+                .collect()
+        }
+        Command::Workspaces => {
+            let mut entries = if input.is_empty() {
+                hyprland
+                    .workspaces
+                    .iter()
+                    .map(|workspace| (workspace, 0))
+                    .collect()
+            } else {
+                search_with(input, &hyprland.workspaces, &config.search)
+            };
+            rank::sort(
+                &mut entries,
+                input,
+                &config.search.tie_breaks,
+                |workspace| SortKeys {
+                    text: &workspace.name,
+                    source: hyprland
+                        .workspaces
+                        .position(workspace.id)
+                        .unwrap_or(usize::MAX),
+                    last_used: None,
+                },
+            );
+            entries.truncate(config.max_entries);
+            entries
+                .into_iter()
+                .map(|(workspace, _)| Match {
+                    title: render(&workspace.name).into(),
+                    icon: ROption::RSome("view-grid-symbolic".into()),
+                    use_pango: config.highlight_matches,
+                    description: ROption::RSome(
+                        render(&format!(
+                            "{} windows on {}: {}",
+                            workspace.windows, workspace.monitor, workspace.last_window_title
+                        ))
+                        .into(),
+                    ),
+                    id: ROption::RSome(workspace.id),
+                })
+                .collect()
+        }
+    };
+    report::with_errors(
+        "hyprwin",
+        [state.config.error(), state.hyprland.error()],
+        matches,
+    )
+}
+
+fn window_match(
+    client: &ClientId,
+    action: Action,
+    actions: &Actions<Action>,
+    render: impl Fn(&str) -> String,
+    use_pango: bool,
+) -> Match {
+    Match {
+        title: render(&client.class).into(),
+        icon: ROption::RSome(icon_from_class(&client.class).into()),
+        use_pango,
+        description: actions
+            .describe(action, Some(render(&client.title)))
+            .map(RString::from)
+            .into(),
+        id: ROption::RSome(actions.id(client.id, action)),
+    }
 }
 
 #[handler]
 fn handler(selection: Match, state: &State) -> HandleResult {
-    // Handle the selected match and return how anyrun should proceed
-    use hyprland::dispatch::*;
-    let Some(address) = state
-        .clients
-        .iter()
-        .find(|c| c.id == selection.id.unwrap_or_default())
-        .map(|c| c.address.clone())
-    else {
+    if report::is_report(&selection) {
         return HandleResult::Close;
+    }
+    // Handle the selected match and return how anyrun should proceed
+    let id = selection.id.into_option();
+    let hyprland = state.hyprland.get();
+    let (client, action) = match actions().resolve(id, |id| hyprland.clients.get(Some(id)).ok()) {
+        Ok(client) => client,
+        Err(why) => {
+            match hyprland.workspaces.get(id) {
+                Ok(workspace) => {
+                    let id = workspace.workspace.id.to_string();
+                    if let Err(why) = dispatch("workspace", &id) {
+                        log::error!("Error switching hyprland workspace: {}", why);
+                    }
+                    state.hyprland.refresh();
+                }
+                Err(_) => log::error!("Error focusing hyprland window: {}", why),
+            }
+            return HandleResult::Close;
+        }
     };
-    Dispatch::call(DispatchType::FocusWindow(WindowIdentifier::Address(
-        address,
-    )))
-    .expect("Unable to focus hyprland window");
+    let window = format!("address:{}", client.address);
+    let dispatched = match action {
+        Action::Focus => {
+            state.frecency.record(&client.class);
+            dispatch("focuswindow", &window)
+        }
+        Action::Close => dispatch("closewindow", &window),
+        // `+0` is the active workspace
+        Action::Move => dispatch("movetoworkspace", &format!("+0,{}", window)),
+    };
+    if let Err(why) = dispatched {
+        log::error!("Error running hyprland dispatcher: {}", why);
+    }
+    // Only now the active workspace and window order changed, a reload before could miss it
+    state.hyprland.refresh();
     HandleResult::Close
 }
 
 fn icon_from_class(class: impl AsRef<str>) -> String {
     let class = class.as_ref().to_lowercase();
     if class.contains('.') {
-        class.split('.').last().unwrap_or_default().into()
+        class.split('.').next_back().unwrap_or_default().into()
     } else {
         class
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_by_focus_history() {
+        let client = |class: &str, focus_history_id: i64| {
+            format!(
+                r#"{{
+                    "address": "0x5f9a2c1b3d40", "mapped": true, "hidden": false, "at": [10, 40],
+                    "size": [1900, 1030], "workspace": {{"id": 2, "name": "2"}},
+                    "floating": false, "monitor": 0, "class": "{}", "title": "{}",
+                    "initialClass": "{}", "initialTitle": "{}", "pid": 4242, "xwayland": false,
+                    "pinned": false, "fullscreen": false, "fullscreenMode": 0, "grouped": [],
+                    "swallowing": "0x0", "focusHistoryID": {}
+                }}"#,
+                class, class, class, class, focus_history_id
+            )
+        };
+        let json = format!(
+            "[{}, {}, {}]",
+            client("kitty", 2),
+            client("firefox", 0),
+            client("foot", 1)
+        );
+        let classes = by_focus_history(json.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|client| client.class)
+            .collect::<Vec<_>>();
+        assert_eq!(classes, ["firefox", "foot", "kitty"]);
+    }
+}
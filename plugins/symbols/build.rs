@@ -9,7 +9,7 @@ fn main() {
     let mut file = File::create(format!("{}/unicode.rs", env::var("OUT_DIR").unwrap()))
         .expect("Unable to create unicode output file!");
 
-    file.write_all(b"#[allow(text_direction_codepoint_in_literal)]\nconst UNICODE_CHARS: &[(&str, &str)] = &[\n")
+    file.write_all(b"const UNICODE_CHARS: &[(&str, &str)] = &[\n")
         .unwrap();
     string.lines().for_each(|line| {
         let fields = line.split(';').collect::<Vec<_>>();
@@ -19,8 +19,11 @@ fn main() {
         };
 
         if fields[1] != "<control>" {
-            file.write_all(format!("(r#\"{}\"#, r#\"{}\"#),\n", fields[1], chr).as_bytes())
-                .unwrap();
+            // Escape the character so direction changing codepoints do not end up in a literal
+            file.write_all(
+                format!("(r#\"{}\"#, \"{}\"),\n", fields[1], chr.escape_unicode()).as_bytes(),
+            )
+            .unwrap();
         }
     });
 
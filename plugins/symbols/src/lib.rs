@@ -1,63 +1,134 @@
-use std::{collections::HashMap, fs};
+use std::collections::HashMap;
 
 use abi_stable::std_types::{ROption, RString, RVec};
 use anyrun_plugin::*;
 use serde::Deserialize;
-use utils::fuzzy_match;
+use utils::{
+    config::Watched,
+    escape,
+    frecency::{self, Frecency},
+    highlight,
+    ids::{stable_id, Registry},
+    log,
+    normalize::Normalize,
+    rank::{self, EmptyQuery, SortKeys},
+    report,
+    router::Router,
+    search,
+    worker::Worker,
+    Field, SearchOptions, Searchable, FULL_WEIGHT,
+};
 
 include!(concat!(env!("OUT_DIR"), "/unicode.rs"));
 
 #[derive(Clone, Debug)]
 struct Symbol {
+    id: u64,
     chr: String,
     name: String,
 }
 
-impl AsRef<str> for Symbol {
-    fn as_ref(&self) -> &str {
-        &self.name
+impl Searchable for Symbol {
+    fn fields(&self) -> Vec<Field<'_>> {
+        vec![Field::new("name", &self.name, FULL_WEIGHT)]
     }
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(default)]
 struct Config {
     prefix: String,
+    /// Other prefixes that work like `prefix`
+    aliases: Vec<String>,
+    /// Whether symbols are only searched when the query starts with a prefix
+    require_prefix: bool,
     symbols: HashMap<String, String>,
     max_entries: usize,
+    highlight_matches: bool,
+    /// What is listed when nothing but the prefix is typed
+    empty_query: EmptyQuery,
+    /// Weak matches left out and tie-breaks. The unicode names are ascii and matched with
+    /// smart case, so transliteration and unicode folding do not apply.
+    search: SearchOptions,
+    frecency_weight: u32,
+    /// Log level and file, read at startup
+    log: log::Config,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             prefix: ":s".to_string(),
+            aliases: Vec::new(),
+            require_prefix: true,
             symbols: HashMap::new(),
             max_entries: 3,
+            highlight_matches: false,
+            empty_query: EmptyQuery::None,
+            search: SearchOptions::default(),
+            frecency_weight: frecency::DEFAULT_WEIGHT,
+            log: log::Config::default(),
         }
     }
 }
 
+impl Config {
+    fn router(&self) -> Router<()> {
+        Router::new(
+            std::iter::once(&self.prefix).chain(&self.aliases),
+            self.require_prefix,
+            (),
+        )
+    }
+
+    /// The user defined symbols sorted by name, with ids that stay the same when others are
+    /// added or removed.
+    fn custom_symbols(&self) -> Registry<Symbol> {
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort();
+        let symbols = symbols.into_iter().map(|(name, chr)| Symbol {
+            id: stable_id(name),
+            chr: chr.clone(),
+            name: name.clone(),
+        });
+        Registry::new(symbols, |symbol| symbol.id)
+    }
+}
+
+/// How many of the best unicode matches frecency can reorder
+const CANDIDATES: usize = 100;
+
 struct State {
-    config: Config,
+    config: Watched<Config>,
     symbols: Vec<Symbol>,
+    /// Matches the unicode symbols in the background, there are too many to do it per keystroke
+    worker: Worker,
+    frecency: Frecency,
 }
 
 #[init]
 fn init(config_dir: RString) -> State {
-    // Try to load the config file, if it does not exist only use the static unicode characters
-    let config = if let Ok(content) = fs::read_to_string(format!("{}/symbols.ron", config_dir)) {
-        ron::from_str(&content).unwrap_or_default()
-    } else {
-        Config::default()
-    };
+    // If the config file does not exist only the static unicode characters are used
+    let config = Watched::<Config>::load(&config_dir, "symbols");
+    log::init("symbols", &config.get().log);
 
     let symbols = UNICODE_CHARS
         .iter()
-        .map(|(name, chr)| (name.to_string(), chr.to_string()))
-        .chain(config.symbols.clone().into_iter())
-        .map(|(name, chr)| Symbol { chr, name })
-        .collect();
+        .enumerate()
+        .map(|(id, (name, chr))| Symbol {
+            id: id as u64,
+            chr: chr.to_string(),
+            name: name.to_string(),
+        })
+        .collect::<Vec<_>>();
+    let worker = Worker::new(&symbols);
 
-    State { config, symbols }
+    State {
+        config,
+        symbols,
+        worker,
+        frecency: Frecency::load("symbols"),
+    }
 }
 
 #[info]
@@ -70,28 +141,87 @@ fn info() -> PluginInfo {
 
 #[get_matches]
 fn get_matches(input: RString, state: &State) -> RVec<Match> {
-    let input = if let Some(input) = input.strip_prefix(&state.config.prefix) {
-        input.trim()
-    } else {
+    let config = state.config.get();
+    let Some(route) = config.router().route(&input) else {
         return RVec::new();
     };
+    let input = route.query;
 
-    let mut symbols = fuzzy_match(input, &state.symbols);
-    symbols.sort_by(|a, b| b.1.cmp(&a.1));
-    symbols.truncate(state.config.max_entries);
-    symbols
+    let custom_symbols = config.custom_symbols();
+    let mut symbols = if input.is_empty() {
+        let symbols = state.symbols.iter().chain(&custom_symbols);
+        config
+            .empty_query
+            .list(symbols, Some(&state.frecency), |symbol| &symbol.name)
+            .into_iter()
+            .map(|symbol| (symbol, 0))
+            .collect()
+    } else {
+        let mut symbols = state
+            .worker
+            .search(input, CANDIDATES.max(config.max_entries))
+            .into_iter()
+            .map(|(i, score)| (&state.symbols[i], score))
+            .chain(search(input, &custom_symbols))
+            .collect::<Vec<_>>();
+        config.search.min_score.retain(&mut symbols);
+        state
+            .frecency
+            .rank(&mut symbols, |symbol| &symbol.name, config.frecency_weight);
+        // Unicode symbols are numbered by their position, the custom ones come after them
+        rank::sort(&mut symbols, input, &config.search.tie_breaks, |symbol| {
+            SortKeys {
+                text: &symbol.name,
+                source: match custom_symbols.position(symbol.id) {
+                    Some(i) => state.symbols.len() + i,
+                    None => symbol.id as usize,
+                },
+                last_used: state.frecency.last_used(&symbol.name),
+            }
+        });
+        symbols
+    };
+    symbols.truncate(config.max_entries);
+    let matches = symbols
         .into_iter()
-        .map(|(symbol, _)| Match {
-            title: symbol.chr.clone().into(),
-            description: ROption::RSome(symbol.name.clone().into()),
-            use_pango: false,
-            icon: ROption::RNone,
-            id: ROption::RNone,
+        .map(|(symbol, _)| {
+            let (title, description) = if config.highlight_matches {
+                (
+                    escape(&symbol.chr),
+                    highlight(input, &symbol.name, &Normalize::default()),
+                )
+            } else {
+                (symbol.chr.clone(), symbol.name.clone())
+            };
+            Match {
+                title: title.into(),
+                description: ROption::RSome(description.into()),
+                use_pango: config.highlight_matches,
+                icon: ROption::RNone,
+                id: ROption::RSome(symbol.id),
+            }
         })
-        .collect()
+        .collect();
+    report::with_errors("symbols", [state.config.error()], matches)
 }
 
 #[handler]
-fn handler(selection: Match) -> HandleResult {
-    HandleResult::Copy(selection.title.into_bytes())
+fn handler(selection: Match, state: &State) -> HandleResult {
+    if report::is_report(&selection) {
+        return HandleResult::Close;
+    }
+    // The title may be escaped markup, so copy the symbol itself
+    let custom_symbols = state.config.get().custom_symbols();
+    let id = selection.id.into_option();
+    match custom_symbols
+        .get(id)
+        .ok()
+        .or_else(|| state.symbols.get(id? as usize))
+    {
+        Some(symbol) => {
+            state.frecency.record(&symbol.name);
+            HandleResult::Copy(symbol.chr.clone().into_bytes().into())
+        }
+        None => HandleResult::Copy(selection.title.into_bytes()),
+    }
 }
@@ -0,0 +1,143 @@
+use crate::ids::{stable_id, UnknownId};
+
+/// Alternate actions selected by a trailing `!name` token, e.g. `firefox !close`.
+///
+/// anyrun only tells a handler which match was selected, so the action is encoded in the
+/// match id: [`id`](Self::id) tags an item id with an action, [`resolve`](Self::resolve)
+/// recovers both from a selected match.
+#[derive(Debug, Clone)]
+pub struct Actions<A> {
+    default: A,
+    actions: Vec<Action<A>>,
+}
+
+#[derive(Debug, Clone)]
+struct Action<A> {
+    name: String,
+    label: String,
+    action: A,
+    tag: u64,
+}
+
+impl<A: Copy + PartialEq> Actions<A> {
+    /// Run `default` unless the query ends with the name of another action.
+    pub fn new(default: A) -> Self {
+        Self {
+            default,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Run `action` when the query ends with `!name`, matches describe it as `label`.
+    pub fn action(mut self, name: &str, label: &str, action: A) -> Self {
+        self.actions.push(Action {
+            name: format!("!{}", name),
+            label: label.to_string(),
+            action,
+            tag: stable_id(&format!("action:{}", name)),
+        });
+        self
+    }
+
+    /// The action selected by the last word of `query`, and the query without that word.
+    ///
+    /// Only known action names are taken off, `!foo` still excludes `foo` otherwise.
+    pub fn parse<'a>(&self, query: &'a str) -> (A, &'a str) {
+        let query = query.trim_end();
+        let (rest, word) = query
+            .rsplit_once(char::is_whitespace)
+            .unwrap_or(("", query));
+        match self.actions.iter().find(|action| action.name == word) {
+            Some(action) => (action.action, rest.trim_end()),
+            None => (self.default, query),
+        }
+    }
+
+    /// `description` prefixed with the label of `action`, unless it is the default action.
+    pub fn describe(&self, action: A, description: Option<String>) -> Option<String> {
+        let Some(label) = self.find(action).map(|action| &action.label) else {
+            return description;
+        };
+        Some(match description {
+            Some(description) => format!("{}: {}", label, description),
+            None => label.clone(),
+        })
+    }
+
+    /// Id of the match that runs `action` on the item with id `id`.
+    pub fn id(&self, id: u64, action: A) -> u64 {
+        match self.find(action) {
+            Some(action) => id ^ action.tag,
+            None => id,
+        }
+    }
+
+    /// The item and action of the selected match `id`, `lookup` finds items by their id.
+    pub fn resolve<T>(
+        &self,
+        id: Option<u64>,
+        lookup: impl Fn(u64) -> Option<T>,
+    ) -> Result<(T, A), UnknownId> {
+        let match_id = id.ok_or(UnknownId(id))?;
+        std::iter::once((0, self.default))
+            .chain(
+                self.actions
+                    .iter()
+                    .map(|action| (action.tag, action.action)),
+            )
+            .find_map(|(tag, action)| Some((lookup(match_id ^ tag)?, action)))
+            .ok_or(UnknownId(id))
+    }
+
+    fn find(&self, action: A) -> Option<&Action<A>> {
+        if action == self.default {
+            return None;
+        }
+        self.actions.iter().find(|entry| entry.action == action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::Registry;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Action {
+        Focus,
+        Close,
+    }
+
+    #[test]
+    fn test_actions() {
+        let actions = Actions::new(Action::Focus).action("close", "Close window", Action::Close);
+
+        assert_eq!(actions.parse("fire !close"), (Action::Close, "fire"));
+        assert_eq!(actions.parse("!close"), (Action::Close, ""));
+        assert_eq!(
+            actions.parse("fire !private"),
+            (Action::Focus, "fire !private")
+        );
+        assert_eq!(actions.parse("!close fire"), (Action::Focus, "!close fire"));
+
+        assert_eq!(
+            actions.describe(Action::Close, Some("Firefox".into())),
+            Some("Close window: Firefox".into())
+        );
+        assert_eq!(actions.describe(Action::Focus, None), None);
+
+        let windows = Registry::new(["firefox", "kitty"], |name| stable_id(name));
+        let lookup = |id| windows.get(Some(id)).ok().copied();
+        let id = actions.id(stable_id("kitty"), Action::Close);
+        assert_eq!(
+            actions.resolve(Some(id), lookup),
+            Ok(("kitty", Action::Close))
+        );
+        let id = actions.id(stable_id("kitty"), Action::Focus);
+        assert_eq!(
+            actions.resolve(Some(id), lookup),
+            Ok(("kitty", Action::Focus))
+        );
+        assert_eq!(actions.resolve(Some(1), lookup), Err(UnknownId(Some(1))));
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{dirs, ids::stable_id, log};
+
+#[derive(Serialize, Deserialize)]
+struct Cached<T> {
+    key: String,
+    fingerprint: u64,
+    data: T,
+}
+
+/// Load data prepared from `sources` from `$XDG_CACHE_HOME/anyrun-plugins/<name>.ron`, or
+/// `build` and store it if the cache is missing or stale.
+///
+/// The cache is stale when `key` changed, e.g. the plugin version, or when any of `sources` or
+/// the files directly in a source directory were modified, added or removed.
+pub fn cached<T, E>(
+    name: &str,
+    key: &str,
+    sources: &[PathBuf],
+    build: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+{
+    match dirs::cache_dir() {
+        Some(dir) => cached_in(&dir.join(format!("{}.ron", name)), key, sources, build),
+        None => build(),
+    }
+}
+
+fn cached_in<T, E>(
+    path: &Path,
+    key: &str,
+    sources: &[PathBuf],
+    build: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let fingerprint = fingerprint(sources);
+    let cached = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| ron::from_str::<Cached<T>>(&content).ok());
+    if let Some(cached) = cached {
+        if cached.key == key && cached.fingerprint == fingerprint {
+            return Ok(cached.data);
+        }
+    }
+
+    log::info!("Rebuilding cache {}", path.display());
+    let cached = Cached {
+        key: key.to_string(),
+        fingerprint,
+        data: build()?,
+    };
+    if let Err(why) = store(path, &cached) {
+        log::warn!("Error writing cache {}: {}", path.display(), why);
+    }
+    Ok(cached.data)
+}
+
+fn store<T: Serialize>(path: &Path, cached: &Cached<T>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write next to the cache and rename, so a concurrent load never sees half a file
+    let tmp = path.with_extension(format!("ron.{}", std::process::id()));
+    fs::write(&tmp, ron::to_string(cached)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Hash of the modification times of `sources` and the files directly in them.
+fn fingerprint(sources: &[PathBuf]) -> u64 {
+    let mut stamp = String::new();
+    for source in sources {
+        push_mtime(&mut stamp, source);
+        if let Ok(dir) = fs::read_dir(source) {
+            let mut files = dir
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .collect::<Vec<_>>();
+            files.sort();
+            for file in files {
+                push_mtime(&mut stamp, &file);
+            }
+        }
+    }
+    stable_id(&stamp)
+}
+
+fn push_mtime(stamp: &mut String, path: &Path) {
+    // Follows symlinks, so a changed link target counts as a change
+    let mtime = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok());
+    let _ = write!(stamp, "{}={:?};", path.display(), mtime);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn test_cached() {
+        let dir = std::env::temp_dir().join(format!("anyrun-plugins-cache-{}", std::process::id()));
+        let sources = dir.join("sources");
+        fs::create_dir_all(&sources).unwrap();
+        let source = sources.join("firefox.desktop");
+        fs::write(&source, "Name=Firefox").unwrap();
+        let path = dir.join("cache.ron");
+
+        let builds = Cell::new(0);
+        let load = |key: &str| {
+            cached_in(&path, key, std::slice::from_ref(&sources), || {
+                builds.set(builds.get() + 1);
+                fs::read_to_string(&source)
+            })
+            .unwrap()
+        };
+
+        assert_eq!(load("v1"), "Name=Firefox");
+        assert_eq!(load("v1"), "Name=Firefox");
+        assert_eq!(builds.get(), 1);
+
+        assert_eq!(load("v2"), "Name=Firefox");
+        assert_eq!(builds.get(), 2);
+
+        // Edited in place, so only the file's own mtime changes
+        fs::write(&source, "Name=Firefox Nightly").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(load("v2"), "Name=Firefox Nightly");
+        assert_eq!(builds.get(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use ron::{error::SpannedError, Value};
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    forward_to_deserialize_any, Deserializer,
+};
+
+use crate::{log, watch::FileWatcher};
+
+/// Load `<config_dir>/<name>.ron`, falling back to the default config if it is missing or broken.
+///
+/// Missing fields keep their default when the config struct is annotated with
+/// `#[serde(default)]`. Unknown fields and parse errors are reported with their location.
+pub fn load<T: DeserializeOwned + Default>(config_dir: &str, name: &str) -> T {
+    let path = path(config_dir, name);
+    match read(&path) {
+        Ok(Some(config)) => config,
+        Ok(None) => T::default(),
+        Err(why) => {
+            log::error!("{}", why);
+            T::default()
+        }
+    }
+}
+
+/// A config that is reloaded when its file changes.
+///
+/// If the changed file does not parse the last good config is kept.
+pub struct Watched<T> {
+    path: PathBuf,
+    config: RwLock<Arc<T>>,
+    error: RwLock<Option<String>>,
+    watcher: Option<FileWatcher>,
+}
+
+impl<T: DeserializeOwned + Default> Watched<T> {
+    /// Load `<config_dir>/<name>.ron` like [`load`] and start watching it.
+    pub fn load(config_dir: &str, name: &str) -> Self {
+        let path = path(config_dir, name);
+        let watcher = FileWatcher::new(&path)
+            .map_err(|why| log::warn!("Error watching {}: {}", path.display(), why))
+            .ok();
+
+        let (config, error) = match read(&path) {
+            Ok(config) => (config.unwrap_or_default(), None),
+            Err(why) => {
+                log::error!("{}", why);
+                (T::default(), Some(why.to_string()))
+            }
+        };
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            error: RwLock::new(error),
+            path,
+            watcher,
+        }
+    }
+
+    /// The current config, reloaded first if the file changed since the last call.
+    pub fn get(&self) -> Arc<T> {
+        if self.watcher.as_ref().is_some_and(FileWatcher::changed) {
+            match read(&self.path) {
+                Ok(config) => {
+                    *self.config.write() = Arc::new(config.unwrap_or_default());
+                    *self.error.write() = None;
+                }
+                Err(why) => {
+                    log::error!("{}, keeping the previous config", why);
+                    *self.error.write() = Some(why.to_string());
+                }
+            }
+        }
+        self.config.read().clone()
+    }
+
+    /// Why the config file does not parse, checked by [`get`](Self::get).
+    pub fn error(&self) -> Option<String> {
+        self.error.read().clone()
+    }
+}
+
+/// Path of the config file of plugin `name`.
+pub fn path(config_dir: &str, name: &str) -> PathBuf {
+    Path::new(config_dir).join(format!("{}.ron", name))
+}
+
+/// Read and parse the config at `path`, `Ok(None)` if the file does not exist.
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Error> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(why) => {
+            return Err(Error::Read {
+                path: path.to_path_buf(),
+                why,
+            })
+        }
+    };
+
+    for (field, suggestion) in unknown_fields::<T>(&content) {
+        match suggestion {
+            Some(suggestion) => log::warn!(
+                "Unknown field `{}` in {}, did you mean `{}`?",
+                field,
+                path.display(),
+                suggestion
+            ),
+            None => log::warn!("Unknown field `{}` in {}", field, path.display()),
+        }
+    }
+
+    ron::from_str(&content)
+        .map(Some)
+        .map_err(|why| Error::Parse {
+            path: path.to_path_buf(),
+            why,
+        })
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read { path: PathBuf, why: io::Error },
+    Parse { path: PathBuf, why: SpannedError },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read { path, why } => write!(f, "Error reading {}: {}", path.display(), why),
+            Error::Parse { path, why } => write!(
+                f,
+                "Error parsing {} at line {}, column {}: {}",
+                path.display(),
+                why.position.line,
+                why.position.col,
+                why.code
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Top level fields of `content` that `T` does not know, with the closest known field name.
+pub fn unknown_fields<T: DeserializeOwned>(content: &str) -> Vec<(String, Option<&'static str>)> {
+    let known = field_names::<T>();
+    let Ok(Value::Map(map)) = ron::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+
+    map.keys()
+        .filter_map(|key| match key {
+            Value::String(key) if !known.contains(&key.as_str()) => Some(key.clone()),
+            _ => None,
+        })
+        .map(|key| {
+            let suggestion = known
+                .iter()
+                .map(|field| (*field, distance(&key, field)))
+                .filter(|(_, distance)| *distance <= key.len().max(3) / 3)
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(field, _)| field);
+            (key, suggestion)
+        })
+        .collect()
+}
+
+/// Field names of the struct `T`, captured from the `deserialize_struct` call serde makes.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct Probe<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for Probe<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields captured"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+            identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Probe(&mut fields));
+    fields
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let cost = if a == *b { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(default)]
+    struct Config {
+        max_entries: usize,
+        prefix: String,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                max_entries: 5,
+                prefix: ":w".into(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = std::env::temp_dir().join(format!("anyrun-plugins-read-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_dir = dir.to_str().unwrap();
+
+        assert!(matches!(
+            read::<Config>(&path(config_dir, "missing")),
+            Ok(None)
+        ));
+
+        let partial = path(config_dir, "partial");
+        fs::write(&partial, "Config(prefix: \":x\")").unwrap();
+        assert_eq!(
+            read::<Config>(&partial).unwrap(),
+            Some(Config {
+                max_entries: 5,
+                prefix: ":x".into()
+            })
+        );
+
+        let broken = path(config_dir, "broken");
+        fs::write(
+            &broken,
+            "Config(\n  prefix: \":x\",\n  max_entries: \"five\",\n)",
+        )
+        .unwrap();
+        let why = read::<Config>(&broken).unwrap_err();
+        assert!(matches!(why, Error::Parse { .. }));
+        assert!(
+            why.to_string().starts_with(&format!(
+                "Error parsing {} at line 3, column 16: ",
+                broken.display()
+            )),
+            "{}",
+            why
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watched_config() {
+        let dir = std::env::temp_dir().join(format!("anyrun-plugins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_dir = dir.to_str().unwrap();
+        let path = path(config_dir, "watched");
+        fs::write(&path, "Config(max_entries: 1)").unwrap();
+
+        let config = Watched::<Config>::load(config_dir, "watched");
+        assert_eq!(config.get().max_entries, 1);
+
+        fs::write(&path, "Config(max_entries: 2)").unwrap();
+        assert_eq!(config.get().max_entries, 2);
+
+        fs::write(&path, "Config(max_entries: ").unwrap();
+        assert_eq!(config.get().max_entries, 2);
+        assert!(config.error().is_some());
+
+        fs::write(&path, "Config(max_entries: 3)").unwrap();
+        assert_eq!(config.get().max_entries, 3);
+        assert_eq!(config.error(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let content = "Config(max_entires: 3, prefix: \":w\", colour: 1)";
+        assert_eq!(
+            unknown_fields::<Config>(content),
+            vec![
+                ("colour".to_string(), None),
+                ("max_entires".to_string(), Some("max_entries")),
+            ]
+        );
+    }
+}
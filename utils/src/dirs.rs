@@ -0,0 +1,21 @@
+use std::{env, path::PathBuf};
+
+const APP_DIR: &str = "anyrun-plugins";
+
+/// `$XDG_STATE_HOME/anyrun-plugins`, falling back to `~/.local/state/anyrun-plugins`.
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CACHE_HOME/anyrun-plugins`, falling back to `~/.cache/anyrun-plugins`.
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let base = match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(fallback),
+    };
+    Some(base.join(APP_DIR))
+}
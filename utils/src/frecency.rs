@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{dirs, log};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// Default percentage of the frecency score that is added to the match score.
+pub const DEFAULT_WEIGHT: u32 = 25;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Usage {
+    count: u32,
+    last_used: u64,
+}
+
+/// How often and how recently items were selected, persisted per plugin under
+/// `$XDG_STATE_HOME/anyrun-plugins/<plugin>-frecency.ron`.
+pub struct Frecency {
+    path: Option<PathBuf>,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl Frecency {
+    /// Load the store of `plugin`, a missing or broken file starts with an empty one.
+    pub fn load(plugin: &str) -> Self {
+        Self::open(dirs::state_dir().map(|dir| dir.join(format!("{}-frecency.ron", plugin))))
+    }
+
+    fn open(path: Option<PathBuf>) -> Self {
+        let usage = path.as_deref().and_then(read).unwrap_or_default();
+        Self {
+            path,
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// Record that the item identified by `key` was selected and save the store.
+    ///
+    /// Selections saved by other running instances since this one loaded the store are merged
+    /// in first, so they are not overwritten.
+    pub fn record(&self, key: &str) {
+        let mut usage = self.usage.lock();
+        let Some(path) = &self.path else {
+            select(&mut usage, key);
+            log::error!("Error saving frecency: Unable to determine state directory");
+            return;
+        };
+
+        // Keeps other instances from saving between the read and the save
+        let lock = lock(path);
+        if let Err(why) = &lock {
+            log::warn!("Error locking frecency, saving anyway: {}", why);
+        }
+        if let Some(stored) = read(path) {
+            merge(&mut usage, stored);
+        }
+        select(&mut usage, key);
+        if let Err(why) = save(path, &usage) {
+            log::error!("Error saving frecency: {}", why);
+        }
+        drop(lock);
+    }
+
+    /// Frecency of `key`, grows with the number of selections and decays with age.
+    pub fn score(&self, key: &str) -> u32 {
+        match self.usage.lock().get(key) {
+            Some(usage) => frecency(usage, now()),
+            None => 0,
+        }
+    }
+
+    /// When `key` was last selected, in seconds since the epoch.
+    pub fn last_used(&self, key: &str) -> Option<u64> {
+        self.usage.lock().get(key).map(|usage| usage.last_used)
+    }
+
+    /// Add `weight` percent of each item's frecency to its match score and sort again.
+    pub fn rank<T>(&self, items: &mut [(T, u32)], key: impl Fn(&T) -> &str, weight: u32) {
+        if weight == 0 {
+            return;
+        }
+
+        let usage = self.usage.lock();
+        let now = now();
+        for (item, score) in items.iter_mut() {
+            if let Some(usage) = usage.get(key(item)) {
+                *score += frecency(usage, now) * weight / 100;
+            }
+        }
+        items.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    }
+}
+
+/// The store at `path`, `None` when it is missing or broken.
+fn read(path: &Path) -> Option<HashMap<String, Usage>> {
+    ron::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn save(path: &Path, usage: &HashMap<String, Usage>) -> Result<(), Box<dyn std::error::Error>> {
+    // Write next to the store and rename, so a crash never leaves half a file
+    let tmp = path.with_extension(format!("ron.{}", std::process::id()));
+    fs::write(&tmp, ron::to_string(usage)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// An exclusive lock on a file next to the store at `path`, released when it is dropped.
+fn lock(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))?;
+    // SAFETY: the file descriptor is open for as long as `file` lives
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Add the selections in `stored` this instance has not seen yet to `usage`.
+fn merge(usage: &mut HashMap<String, Usage>, stored: HashMap<String, Usage>) {
+    for (key, stored) in stored {
+        let entry = usage.entry(key).or_insert(stored);
+        // Every instance saves all its selections, so the store never has fewer
+        entry.count = entry.count.max(stored.count);
+        entry.last_used = entry.last_used.max(stored.last_used);
+    }
+}
+
+fn select(usage: &mut HashMap<String, Usage>, key: &str) {
+    let entry = usage.entry(key.to_string()).or_insert(Usage {
+        count: 0,
+        last_used: 0,
+    });
+    entry.count = entry.count.saturating_add(1);
+    entry.last_used = now();
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+fn frecency(usage: &Usage, now: u64) -> u32 {
+    // Recency buckets as used by Firefox's frecency
+    let recency = match now.saturating_sub(usage.last_used) {
+        age if age < 4 * HOUR => 100.0,
+        age if age < DAY => 80.0,
+        age if age < 7 * DAY => 60.0,
+        age if age < 30 * DAY => 40.0,
+        age if age < 90 * DAY => 20.0,
+        _ => 10.0,
+    };
+    // Logarithmic in the count, so heavy use does not drown out the match score
+    (recency * (1.0 + usage.count as f64).ln()) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frecency_rank() {
+        let frecency = Frecency {
+            path: None,
+            usage: Mutex::new(HashMap::from([
+                (
+                    "old".to_string(),
+                    Usage {
+                        count: 50,
+                        last_used: now() - 365 * DAY,
+                    },
+                ),
+                (
+                    "recent".to_string(),
+                    Usage {
+                        count: 5,
+                        last_used: now(),
+                    },
+                ),
+            ])),
+        };
+
+        let mut items = vec![("never", 100), ("old", 100), ("recent", 100)];
+        frecency.rank(&mut items, |item| item, 100);
+        assert_eq!(items, vec![("recent", 279), ("old", 139), ("never", 100)]);
+        assert_eq!(frecency.score("never"), 0);
+    }
+
+    #[test]
+    fn test_frecency_instances() {
+        let dir =
+            std::env::temp_dir().join(format!("anyrun-plugins-frecency-{}", std::process::id()));
+        let path = dir.join("test-frecency.ron");
+        let first = Frecency::open(Some(path.clone()));
+        let second = Frecency::open(Some(path.clone()));
+
+        first.record("firefox");
+        second.record("kitty");
+        second.record("firefox");
+        // The second instance saw the first one's selection before saving its own
+        assert_eq!(read(&path).unwrap()["firefox"].count, 2);
+
+        let third = Frecency::open(Some(path.clone()));
+        assert!(third.last_used("firefox").is_some());
+        assert!(third.last_used("kitty").is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use nucleo::pattern::Pattern;
+use nucleo::{Config, Utf32Str};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::normalize::{Folder, Normalize};
+use crate::MATCHER;
+
+/// Score and sorted character indices of every term of `pattern` found in `text`.
+///
+/// Both are folded by `normalize` like [`search_with`](crate::search_with) does, the indices
+/// are those of the characters of the original `text`. Unlike a search, terms that are not
+/// found are skipped instead of rejecting the text, since they may have matched another field
+/// of the same item.
+pub fn match_indices(pattern: &str, text: &str, normalize: &Normalize) -> Option<(u32, Vec<u32>)> {
+    let mut matcher = MATCHER.lock();
+    matcher.config = Config::DEFAULT;
+    let folder = normalize.folder(pattern);
+    let pattern = Pattern::parse(&folder.fold(pattern), folder.case_matching());
+    let (folded, origins) = fold(&folder, text);
+
+    let mut buf = Vec::new();
+    let mut indices = Vec::new();
+    let mut score = None;
+    for atom in pattern.atoms.iter().filter(|atom| !atom.negative) {
+        if let Some(atom_score) =
+            atom.indices(Utf32Str::new(&folded, &mut buf), &mut matcher, &mut indices)
+        {
+            *score.get_or_insert(0) += atom_score as u32;
+        }
+    }
+    let mut indices = indices
+        .into_iter()
+        .map(|i| origins[i as usize])
+        .collect::<Vec<_>>();
+    indices.sort_unstable();
+    indices.dedup();
+
+    score.map(|score| (score, indices))
+}
+
+/// `text` folded character by character, and for every character of the folded text the index
+/// of the character of `text` it came from. Folding can change the length, `ß` becomes `ss`.
+fn fold(folder: &Folder, text: &str) -> (String, Vec<u32>) {
+    let mut folded = String::with_capacity(text.len());
+    // Where the folding of every character of `text` starts in `folded`
+    let mut starts = Vec::new();
+    for chr in characters(text) {
+        starts.push(folded.len());
+        folded.push_str(&folder.fold(chr));
+    }
+    let origins = character_offsets(&folded)
+        .into_iter()
+        .map(|offset| (starts.partition_point(|start| *start <= offset) - 1) as u32)
+        .collect();
+    (folded, origins)
+}
+
+/// The characters as nucleo indexes them: ascii text by byte, everything else by grapheme.
+fn characters(text: &str) -> Vec<&str> {
+    if text.is_ascii() {
+        (0..text.len()).map(|i| &text[i..i + 1]).collect()
+    } else {
+        text.graphemes(true).collect()
+    }
+}
+
+/// Byte offsets of the [`characters`] of `text`.
+fn character_offsets(text: &str) -> Vec<usize> {
+    if text.is_ascii() {
+        (0..text.len()).collect()
+    } else {
+        text.grapheme_indices(true)
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+}
+
+/// Render `text` as escaped Pango markup with the characters at `indices` in bold.
+pub fn markup(text: &str, indices: &[u32]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut bold = false;
+    for (i, chr) in characters(text).into_iter().enumerate() {
+        let matched = indices.binary_search(&(i as u32)).is_ok();
+        if matched != bold {
+            out.push_str(if matched { "<b>" } else { "</b>" });
+            bold = matched;
+        }
+        out.push_str(&escape(chr));
+    }
+    if bold {
+        out.push_str("</b>");
+    }
+    out
+}
+
+/// Render `text` as escaped Pango markup with the characters matched by `pattern` in bold.
+///
+/// `normalize` should be the one the text was searched with, so everything the search matched
+/// is highlighted.
+pub fn highlight(pattern: &str, text: &str, normalize: &Normalize) -> String {
+    match match_indices(pattern, text, normalize) {
+        Some((_, indices)) => markup(text, &indices),
+        None => escape(text),
+    }
+}
+
+/// Escape the characters that have a meaning in Pango markup.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for chr in text.chars() {
+        match chr {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\'' => out.push_str("&apos;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(chr),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::Case;
+
+    #[test]
+    fn test_highlight() {
+        let highlight = |pattern, text| highlight(pattern, text, &Normalize::default());
+        assert_eq!(highlight("fb", "foo<bar>"), "<b>f</b>oo&lt;<b>b</b>ar&gt;");
+        assert_eq!(highlight("'bar !baz", "foobar"), "foo<b>bar</b>");
+        assert_eq!(highlight("中", "中文 & co"), "<b>中</b>文 &amp; co");
+        assert_eq!(highlight("xyz", "a & b"), "a &amp; b");
+    }
+
+    #[test]
+    fn test_highlight_folded() {
+        let normalize = Normalize {
+            locale: Some("en_US.UTF-8".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            highlight("full", "ＦＵＬＬ width", &normalize),
+            "<b>ＦＵＬＬ</b> width"
+        );
+        assert_eq!(
+            highlight("'cafe", "Café Noir", &normalize),
+            "<b>Café</b> Noir"
+        );
+        // `ss` comes from the single `ß`
+        assert_eq!(highlight("'strasse", "Straße", &normalize), "<b>Straße</b>");
+
+        let ignore_case = Normalize {
+            case: Case::Ignore,
+            ..normalize
+        };
+        assert_eq!(highlight("Fire", "firefox", &ignore_case), "<b>fire</b>fox");
+    }
+}
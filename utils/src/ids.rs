@@ -0,0 +1,99 @@
+use std::{collections::HashMap, fmt, ops::Deref};
+
+/// Stable id of the item identified by `key`, the same across runs and builds.
+pub fn stable_id(key: &str) -> u64 {
+    // 64 bit FNV-1a, std's hashers are not guaranteed to be stable
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Items indexed by their id, for looking up the item of a selected match.
+pub struct Registry<T> {
+    items: Vec<T>,
+    index: HashMap<u64, usize>,
+}
+
+impl<T> Registry<T> {
+    /// Index `items` by `id`, if several items share an id the first one is kept.
+    pub fn new(items: impl IntoIterator<Item = T>, id: impl Fn(&T) -> u64) -> Self {
+        let mut index = HashMap::new();
+        let items = items
+            .into_iter()
+            .filter(|item| {
+                let next = index.len();
+                *index.entry(id(item)).or_insert(next) == next
+            })
+            .collect();
+
+        Self { items, index }
+    }
+
+    /// The item with `id`, usually the id of the selected `Match`.
+    pub fn get(&self, id: Option<u64>) -> Result<&T, UnknownId> {
+        id.and_then(|id| self.index.get(&id))
+            .map(|&i| &self.items[i])
+            .ok_or(UnknownId(id))
+    }
+
+    /// Position of the item with `id`, items keep the order they were added in.
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.index.get(&id).copied()
+    }
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Deref for Registry<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Registry<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// The id of a selected match does not belong to any item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownId(pub Option<u64>);
+
+impl fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(id) => write!(f, "No entry with id {:#x}", id),
+            None => write!(f, "Selected match has no id"),
+        }
+    }
+}
+
+impl std::error::Error for UnknownId {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::new(["firefox", "kitty", "firefox"], |item| stable_id(item));
+        assert_eq!(registry.len(), 2);
+        assert_eq!(stable_id("kitty"), 0x3770eb8d911b7500);
+        assert_eq!(registry.get(Some(stable_id("kitty"))), Ok(&"kitty"));
+        assert_eq!(registry.get(Some(1)), Err(UnknownId(Some(1))));
+        assert_eq!(registry.get(None), Err(UnknownId(None)));
+    }
+}
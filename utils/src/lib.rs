@@ -5,6 +5,29 @@ use std::ops::DerefMut;
 use nucleo::pattern::{Atom, AtomKind, CaseMatching};
 use nucleo::Config;
 use parking_lot::Mutex;
+use serde::Deserialize;
+
+pub mod actions;
+pub mod cache;
+pub mod config;
+pub mod dirs;
+pub mod frecency;
+mod highlight;
+pub mod ids;
+pub mod log;
+pub mod normalize;
+pub mod rank;
+pub mod refresh;
+pub mod report;
+pub mod router;
+mod search;
+pub mod spawn;
+mod translit;
+pub mod watch;
+pub mod worker;
+
+pub use highlight::{escape, highlight, markup, match_indices};
+pub use search::{search, search_with, Field, SearchOptions, Searchable, FULL_WEIGHT};
 
 struct LazyMutex<T> {
     inner: Mutex<Option<T>>,
@@ -36,6 +59,29 @@ pub fn fuzzy_match<T: AsRef<str>>(
     pattern.match_list(items, &mut matcher)
 }
 
+/// Minimum a match has to score, so letters scattered over a long text do not show up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MinScore {
+    /// Matches scoring less than this are dropped
+    pub absolute: u32,
+    /// Matches scoring less than this percentage of the best match are dropped
+    pub relative: u32,
+}
+
+impl MinScore {
+    /// Drop the matches scoring below the minimum, the others keep their order.
+    pub fn retain<T, S: Copy + Into<u32>>(&self, matches: &mut Vec<(T, S)>) {
+        let best = matches
+            .iter()
+            .map(|(_, score)| (*score).into())
+            .max()
+            .unwrap_or_default();
+        let min = self.absolute.max(best * self.relative / 100);
+        matches.retain(|(_, score)| (*score).into() >= min);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +95,37 @@ mod tests {
             vec![("foo/bar", 88), ("foobar", 88), ("bar/foo", 84)]
         );
     }
+
+    #[test]
+    fn test_min_score() {
+        let items = vec!["foobar", "bar/foo", "flat shoe tool"];
+        let mut result = fuzzy_match("foo", items);
+        assert_eq!(
+            result,
+            vec![("foobar", 88), ("bar/foo", 84), ("flat shoe tool", 60)]
+        );
+
+        MinScore::default().retain(&mut result);
+        assert_eq!(result.len(), 3);
+
+        let absolute = MinScore {
+            absolute: 85,
+            relative: 0,
+        };
+        let mut absolute_result = result.clone();
+        absolute.retain(&mut absolute_result);
+        assert_eq!(absolute_result, vec![("foobar", 88)]);
+
+        let relative = MinScore {
+            absolute: 0,
+            relative: 75,
+        };
+        relative.retain(&mut result);
+        assert_eq!(result, vec![("foobar", 88), ("bar/foo", 84)]);
+
+        // Nothing to compare against
+        let mut empty: Vec<(&str, u16)> = Vec::new();
+        relative.retain(&mut empty);
+        assert!(empty.is_empty());
+    }
 }
@@ -0,0 +1,218 @@
+use std::{
+    fmt::{self, Arguments},
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::dirs;
+
+/// Log files larger than this are moved to `<name>.log.old` at startup.
+const MAX_FILE_SIZE: u64 = 1 << 20;
+/// Messages logged before [`init`] that are kept for the log file.
+const MAX_PENDING: usize = 100;
+
+/// Severity of a log message, a [`Config`] level also logs everything more severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        })
+    }
+}
+
+/// The `log` field of a plugin config.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The least severe messages that are logged
+    pub level: Level,
+    /// Also append messages to `$XDG_STATE_HOME/anyrun-plugins/<plugin>.log`
+    pub file: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            level: Level::Warn,
+            file: false,
+        }
+    }
+}
+
+struct Logger {
+    name: Option<String>,
+    level: Level,
+    file: Option<File>,
+    /// Messages from before `init`, e.g. config errors, written to the file once it is opened
+    pending: Vec<String>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    name: None,
+    level: Level::Warn,
+    file: None,
+    pending: Vec::new(),
+});
+
+/// Tag the messages of this plugin with `name` and apply `config`.
+///
+/// Messages are always written to stderr. Until this is called they are untagged and logged at
+/// the default level.
+pub fn init(name: &str, config: &Config) {
+    let mut logger = LOGGER.lock();
+    logger.name = Some(name.to_string());
+    logger.level = config.level;
+    logger.file = None;
+
+    let pending = std::mem::take(&mut logger.pending);
+    if !config.file {
+        return;
+    }
+    match open(name) {
+        Ok(mut file) => {
+            for line in pending {
+                let _ = file.write_all(line.as_bytes());
+            }
+            logger.file = Some(file);
+        }
+        Err(why) => {
+            drop(logger);
+            crate::log::error!("Error opening the log file: {}", why);
+        }
+    }
+}
+
+fn open(name: &str) -> Result<File, Box<dyn std::error::Error>> {
+    let dir = dirs::state_dir().ok_or("no state directory")?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.log", name));
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_FILE_SIZE) {
+        fs::rename(&path, PathBuf::from(format!("{}.old", path.display())))?;
+    }
+    Ok(File::options().create(true).append(true).open(&path)?)
+}
+
+/// Log a message, use the [`error!`](crate::log::error), [`warn!`](crate::log::warn),
+/// [`info!`](crate::log::info) and [`debug!`](crate::log::debug) macros instead.
+#[doc(hidden)]
+pub fn log(level: Level, message: Arguments) {
+    let mut logger = LOGGER.lock();
+    if level > logger.level {
+        return;
+    }
+    let message = match &logger.name {
+        Some(name) => format!("[{}] {}: {}", name, level, message),
+        None => format!("{}: {}", level, message),
+    };
+    eprintln!("{}", message);
+
+    let line = format!("{} {}\n", timestamp(SystemTime::now()), message);
+    if logger.name.is_none() {
+        if logger.pending.len() < MAX_PENDING {
+            logger.pending.push(line);
+        }
+    } else if let Some(file) = &mut logger.file {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// `time` as an UTC date and time, e.g. `2024-02-29 13:37:00`.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since the epoch, counted in 400 year eras starting at March 1st
+    let days = days as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __log {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::$level, format_args!($($arg)*))
+    };
+}
+
+/// Log an error, something the user should fix or that made an action fail.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __error {
+    ($($arg:tt)*) => { $crate::__log!(Error, $($arg)*) };
+}
+
+/// Log a warning, something that went wrong but was recovered from.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __warn {
+    ($($arg:tt)*) => { $crate::__log!(Warn, $($arg)*) };
+}
+
+/// Log what a plugin is doing, e.g. that it reloaded its data.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __info {
+    ($($arg:tt)*) => { $crate::__log!(Info, $($arg)*) };
+}
+
+/// Log details that help debugging.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __debug {
+    ($($arg:tt)*) => { $crate::__log!(Debug, $($arg)*) };
+}
+
+pub use crate::{__debug as debug, __error as error, __info as info, __warn as warn};
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01 00:00:00");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1709213820);
+        assert_eq!(timestamp(leap_day), "2024-02-29 13:37:00");
+        let new_year = UNIX_EPOCH + Duration::from_secs(1735689599);
+        assert_eq!(timestamp(new_year), "2024-12-31 23:59:59");
+    }
+}
@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::env;
+
+use nucleo::pattern::CaseMatching;
+use serde::Deserialize;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Unicode folding applied to both the query and the searched text before matching.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Normalize {
+    /// Fold compatibility characters, e.g. full-width `Ａ` to `A` or the `ﬁ` ligature to `fi`
+    pub compatibility: bool,
+    /// Strip diacritics, so `cafe` and `café` both match `Café`
+    pub diacritics: bool,
+    pub case: Case,
+    /// Language whose case rules apply, e.g. `tr` lowercases `I` to `ı`. Defaults to the
+    /// language of `LC_ALL`, `LC_CTYPE` or `LANG`.
+    pub locale: Option<String>,
+}
+
+impl Default for Normalize {
+    fn default() -> Self {
+        Self {
+            compatibility: true,
+            diacritics: true,
+            case: Case::Smart,
+            locale: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Case {
+    /// Ignore case unless the query contains an uppercase letter
+    Smart,
+    Ignore,
+    Respect,
+}
+
+impl Normalize {
+    /// The folding for `query`, which decides whether smart case ignores case.
+    pub(crate) fn folder(&self, query: &str) -> Folder {
+        let ignore_case = match self.case {
+            Case::Smart => !query.chars().any(char::is_uppercase),
+            Case::Ignore => true,
+            Case::Respect => false,
+        };
+        let locale = self.locale.clone().or_else(|| {
+            ["LC_ALL", "LC_CTYPE", "LANG"]
+                .into_iter()
+                .filter_map(|var| env::var(var).ok())
+                .find(|value| !value.is_empty())
+        });
+        // Only the language part of e.g. `tr_TR.UTF-8` matters
+        let turkic = locale.is_some_and(|locale| {
+            matches!(locale.split(['_', '-', '.']).next(), Some("tr" | "az"))
+        });
+
+        Folder {
+            compatibility: self.compatibility,
+            diacritics: self.diacritics,
+            ignore_case,
+            turkic,
+        }
+    }
+}
+
+pub(crate) struct Folder {
+    compatibility: bool,
+    diacritics: bool,
+    ignore_case: bool,
+    turkic: bool,
+}
+
+impl Folder {
+    pub fn case_matching(&self) -> CaseMatching {
+        if self.ignore_case {
+            CaseMatching::Ignore
+        } else {
+            CaseMatching::Respect
+        }
+    }
+
+    pub fn fold<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        // nucleo already ignores the case of ascii letters
+        if text.is_ascii() && !(self.ignore_case && self.turkic && text.contains('I')) {
+            return Cow::Borrowed(text);
+        }
+
+        // Case first, the dot of the Turkish `İ` would be stripped as a diacritic otherwise
+        let text = if self.ignore_case {
+            Cow::Owned(self.fold_case(text))
+        } else {
+            Cow::Borrowed(text)
+        };
+
+        match (self.compatibility, self.diacritics) {
+            (false, false) => text,
+            (true, false) => Cow::Owned(text.nfkc().collect()),
+            (false, true) => Cow::Owned(strip_marks(text.nfd())),
+            (true, true) => Cow::Owned(strip_marks(text.nfkd())),
+        }
+    }
+
+    fn fold_case(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for chr in text.chars() {
+            match chr {
+                'I' if self.turkic => out.push('ı'),
+                'İ' if self.turkic => out.push('i'),
+                'ß' | 'ẞ' => out.push_str("ss"),
+                'ς' => out.push('σ'),
+                _ => out.extend(chr.to_lowercase()),
+            }
+        }
+        out
+    }
+}
+
+fn strip_marks(decomposed: impl Iterator<Item = char>) -> String {
+    decomposed
+        .filter(|chr| !is_combining_mark(*chr))
+        .nfc()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fold(normalize: &Normalize, query: &str, text: &str) -> String {
+        normalize.folder(query).fold(text).into_owned()
+    }
+
+    #[test]
+    fn test_fold() {
+        let normalize = Normalize {
+            locale: Some("en_US.UTF-8".into()),
+            ..Default::default()
+        };
+        assert_eq!(fold(&normalize, "cafe", "Café"), "cafe");
+        assert_eq!(fold(&normalize, "cafe", "ＣＡＦＥ ﬁle"), "cafe file");
+        assert_eq!(fold(&normalize, "strasse", "Straße"), "strasse");
+        assert_eq!(fold(&normalize, "İ", "Ça"), "Ca");
+        assert_eq!(fold(&normalize, "x", "İstanbul"), "istanbul");
+
+        let turkish = Normalize {
+            locale: Some("tr_TR.UTF-8".into()),
+            ..Default::default()
+        };
+        assert_eq!(fold(&turkish, "x", "IŞIK"), "ısık");
+        assert_eq!(fold(&turkish, "x", "İzmir"), "izmir");
+
+        let exact = Normalize {
+            compatibility: false,
+            diacritics: false,
+            case: Case::Respect,
+            locale: None,
+        };
+        assert_eq!(fold(&exact, "cafe", "Café"), "Café");
+    }
+}
@@ -0,0 +1,190 @@
+use std::cmp::Reverse;
+
+use serde::Deserialize;
+
+use crate::frecency::Frecency;
+
+/// A secondary sort key, deciding the order of matches with the same score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TieBreak {
+    /// Items whose text starts with the first search term
+    Prefix,
+    /// Items with a shorter text, which the query covers more of
+    Shorter,
+    /// Recently selected items
+    Recent,
+    /// The order the plugin lists its items in
+    Source,
+}
+
+/// Tie-breaks used when a plugin config does not set any.
+pub const DEFAULT_TIE_BREAKS: &[TieBreak] = &[
+    TieBreak::Prefix,
+    TieBreak::Shorter,
+    TieBreak::Recent,
+    TieBreak::Source,
+];
+
+/// What the tie-breaks of a match look at.
+pub struct SortKeys<'a> {
+    /// The text the item is shown by, e.g. an application's name
+    pub text: &'a str,
+    /// Position of the item in the plugin's item list
+    pub source: usize,
+    /// When the item was last selected, see [`Frecency::last_used`](crate::frecency::Frecency::last_used)
+    pub last_used: Option<u64>,
+}
+
+/// Sort `matches` best first, ordering equal scores by `tie_breaks` in turn.
+///
+/// The source position always breaks the remaining ties, so the same matches and state give
+/// the same order on every run.
+pub fn sort<T>(
+    matches: &mut [(T, u32)],
+    query: &str,
+    tie_breaks: &[TieBreak],
+    keys: impl Fn(&T) -> SortKeys<'_>,
+) {
+    let term = first_term(query).map(str::to_lowercase);
+    matches.sort_by_cached_key(|(item, score)| {
+        let keys = keys(item);
+        let mut order = tie_breaks
+            .iter()
+            .map(|tie_break| match tie_break {
+                TieBreak::Prefix => {
+                    let prefix = term
+                        .as_ref()
+                        .is_some_and(|term| keys.text.to_lowercase().starts_with(term));
+                    !prefix as u64
+                }
+                TieBreak::Shorter => keys.text.chars().count() as u64,
+                TieBreak::Recent => u64::MAX - keys.last_used.unwrap_or_default(),
+                TieBreak::Source => keys.source as u64,
+            })
+            .collect::<Vec<_>>();
+        order.push(keys.source as u64);
+        (Reverse(*score), order)
+    });
+}
+
+/// What a plugin lists when the query is empty, e.g. only its prefix was typed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum EmptyQuery {
+    /// Nothing
+    None,
+    /// All items in the plugin's own order
+    Source,
+    /// All items, the most recently selected ones first
+    Recent,
+    /// All items, the most often and recently selected ones first
+    Frequent,
+    /// The items with these keys, in this order
+    Pinned(Vec<String>),
+}
+
+impl EmptyQuery {
+    /// The items to list for an empty query, best first.
+    ///
+    /// `key` identifies an item for pinning and in `frecency`. Without a frecency store
+    /// `Recent` and `Frequent` keep the plugin's own order.
+    pub fn list<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        frecency: Option<&Frecency>,
+        key: impl Fn(&T) -> &str,
+    ) -> Vec<T> {
+        let mut items = match self {
+            EmptyQuery::None => return Vec::new(),
+            EmptyQuery::Pinned(keys) => {
+                let mut items = items.into_iter().map(Some).collect::<Vec<_>>();
+                return keys
+                    .iter()
+                    .filter_map(|pinned| {
+                        items
+                            .iter_mut()
+                            .find(|item| item.as_ref().is_some_and(|item| key(item) == pinned))?
+                            .take()
+                    })
+                    .collect();
+            }
+            _ => items.into_iter().collect::<Vec<_>>(),
+        };
+
+        // Stable sorts, items never selected keep the plugin's order after the others
+        match (self, frecency) {
+            (EmptyQuery::Recent, Some(frecency)) => items.sort_by_cached_key(|item| {
+                Reverse(frecency.last_used(key(item)).unwrap_or_default())
+            }),
+            (EmptyQuery::Frequent, Some(frecency)) => {
+                items.sort_by_cached_key(|item| Reverse(frecency.score(key(item))))
+            }
+            _ => {}
+        }
+        items
+    }
+}
+
+/// The text of the first term of `query` that has to be found, without its fzf syntax.
+fn first_term(query: &str) -> Option<&str> {
+    query
+        .split_whitespace()
+        .filter(|term| !term.starts_with('!'))
+        .map(|term| term.trim_start_matches(['^', '\'']).trim_end_matches('$'))
+        .find(|term| !term.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort() {
+        // (text, last used), listed in source order
+        let items = [
+            ("bar/foo", None),
+            ("foobar", Some(10)),
+            ("foo/bar", Some(20)),
+            ("foo", None),
+        ];
+        let sorted = |tie_breaks: &[TieBreak]| {
+            let mut matches = vec![(0, 88), (1, 88), (2, 88), (3, 90)];
+            sort(&mut matches, "^foo", tie_breaks, |&i| SortKeys {
+                text: items[i].0,
+                source: i,
+                last_used: items[i].1,
+            });
+            matches
+                .into_iter()
+                .map(|(i, _)| items[i].0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted(&[]), ["foo", "bar/foo", "foobar", "foo/bar"]);
+        assert_eq!(
+            sorted(DEFAULT_TIE_BREAKS),
+            ["foo", "foobar", "foo/bar", "bar/foo"]
+        );
+        assert_eq!(
+            sorted(&[TieBreak::Recent]),
+            ["foo", "foo/bar", "foobar", "bar/foo"]
+        );
+    }
+
+    #[test]
+    fn test_empty_query() {
+        let items = ["firefox", "kitty", "files"];
+        let list = |empty_query: EmptyQuery| empty_query.list(items, None, |item| item);
+
+        assert!(list(EmptyQuery::None).is_empty());
+        assert_eq!(list(EmptyQuery::Source), items);
+        assert_eq!(list(EmptyQuery::Recent), items);
+        assert_eq!(
+            list(EmptyQuery::Pinned(vec![
+                "kitty".into(),
+                "steam".into(),
+                "firefox".into()
+            ])),
+            ["kitty", "firefox"]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use parking_lot::{Condvar, Mutex, RwLock};
+
+use crate::log;
+
+/// Data that is reloaded by a background thread, so reading it never waits for a reload.
+///
+/// The data is reloaded every `interval` and whenever [`refresh`](Self::refresh) is called,
+/// then swapped in at once. A failed reload keeps the previous data, its error is kept until a
+/// reload succeeds.
+pub struct Refreshed<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    data: RwLock<Arc<T>>,
+    error: RwLock<Option<String>>,
+    loaded: AtomicBool,
+    signal: Mutex<Signal>,
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct Signal {
+    triggered: bool,
+    stopped: bool,
+}
+
+impl<T: Default + Send + Sync + 'static> Refreshed<T> {
+    /// Load the data with `load` and keep reloading it on a thread named after `name`.
+    ///
+    /// The first load happens right away on the calling thread, if it fails the data starts
+    /// out as the default. Without an `interval` the data is only reloaded on
+    /// [`refresh`](Self::refresh).
+    pub fn spawn<E: Display>(
+        name: &str,
+        interval: Option<Duration>,
+        load: impl Fn() -> Result<T, E> + Send + 'static,
+    ) -> Self {
+        let (data, error) = match load() {
+            Ok(data) => (data, None),
+            Err(why) => {
+                log::error!("Error loading {}: {}", name, why);
+                (T::default(), Some(why.to_string()))
+            }
+        };
+        let shared = Arc::new(Shared {
+            data: RwLock::new(Arc::new(data)),
+            loaded: AtomicBool::new(error.is_none()),
+            error: RwLock::new(error),
+            signal: Mutex::new(Signal::default()),
+            wake: Condvar::new(),
+        });
+
+        let thread_shared = shared.clone();
+        let thread_name = name.to_string();
+        let spawned = thread::Builder::new()
+            .name(format!("{}-refresh", name))
+            .spawn(move || reload(&thread_shared, &thread_name, interval, load));
+        if let Err(why) = spawned {
+            log::error!("Error starting the refresh of {}: {}", name, why);
+        }
+
+        Self { shared }
+    }
+}
+
+impl<T> Refreshed<T> {
+    /// The latest data.
+    pub fn get(&self) -> Arc<T> {
+        self.shared.data.read().clone()
+    }
+
+    /// Why the latest load failed, `None` once a load succeeded.
+    pub fn error(&self) -> Option<String> {
+        self.shared.error.read().clone()
+    }
+
+    /// Whether a load ever succeeded, until then the data is only the default.
+    pub fn loaded(&self) -> bool {
+        self.shared.loaded.load(Ordering::Relaxed)
+    }
+
+    /// Reload the data in the background as soon as possible, e.g. after an action changed it.
+    pub fn refresh(&self) {
+        self.shared.signal.lock().triggered = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+impl<T> Drop for Refreshed<T> {
+    fn drop(&mut self) {
+        self.shared.signal.lock().stopped = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+fn reload<T, E: Display>(
+    shared: &Shared<T>,
+    name: &str,
+    interval: Option<Duration>,
+    load: impl Fn() -> Result<T, E>,
+) {
+    loop {
+        {
+            let mut signal = shared.signal.lock();
+            if !signal.triggered && !signal.stopped {
+                match interval {
+                    Some(interval) => {
+                        shared.wake.wait_for(&mut signal, interval);
+                    }
+                    None => shared.wake.wait(&mut signal),
+                }
+            }
+            if signal.stopped {
+                return;
+            }
+            signal.triggered = false;
+        }
+
+        match load() {
+            Ok(data) => {
+                *shared.data.write() = Arc::new(data);
+                *shared.error.write() = None;
+                shared.loaded.store(true, Ordering::Relaxed);
+                log::debug!("Refreshed {}", name);
+            }
+            Err(why) => {
+                log::warn!("Error refreshing {}: {}", name, why);
+                *shared.error.write() = Some(why.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn wait_until(done: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_refreshed() {
+        let loads = Arc::new(AtomicU32::new(0));
+        let counter = loads.clone();
+        let refreshed = Refreshed::spawn("test", None, move || {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                1 => Err("broken"),
+                count => Ok(count),
+            }
+        });
+        assert_eq!(*refreshed.get(), 0);
+        assert!(refreshed.loaded());
+
+        // A failed reload keeps the previous data
+        refreshed.refresh();
+        wait_until(|| refreshed.error().is_some());
+        assert_eq!(refreshed.error().as_deref(), Some("broken"));
+        assert_eq!(*refreshed.get(), 0);
+
+        refreshed.refresh();
+        wait_until(|| *refreshed.get() == 2);
+        assert_eq!(refreshed.error(), None);
+    }
+
+    #[test]
+    fn test_refreshed_first_load_failed() {
+        let loads = Arc::new(AtomicU32::new(0));
+        let counter = loads.clone();
+        let refreshed = Refreshed::spawn("test", None, move || {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("broken"),
+                count => Ok(count),
+            }
+        });
+        assert!(!refreshed.loaded());
+        assert_eq!(refreshed.error().as_deref(), Some("broken"));
+
+        refreshed.refresh();
+        wait_until(|| refreshed.loaded());
+        assert_eq!(*refreshed.get(), 1);
+    }
+}
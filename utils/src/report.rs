@@ -0,0 +1,48 @@
+use abi_stable::std_types::{ROption, RVec};
+use anyrun_plugin::Match;
+
+use crate::refresh::Refreshed;
+
+/// A match telling the user why `plugin` is not working, e.g. that cliphist is not installed.
+///
+/// It has no id, so handlers can tell it apart with [`is_report`].
+pub fn error_match(plugin: &str, why: &str) -> Match {
+    Match {
+        title: why.into(),
+        description: ROption::RSome(format!("The {} plugin is not working", plugin).into()),
+        use_pango: false,
+        icon: ROption::RSome("dialog-error".into()),
+        id: ROption::RNone,
+    }
+}
+
+/// The only match to show while `data` never loaded, there is nothing else to show but why.
+///
+/// Once a load succeeded a failed reload keeps the previous data, report its error with
+/// [`with_errors`] instead.
+pub fn unavailable<T>(plugin: &str, data: &Refreshed<T>) -> Option<RVec<Match>> {
+    let why = data.error().filter(|_| !data.loaded())?;
+    Some(vec![error_match(plugin, &why)].into())
+}
+
+/// `matches` after an [`error_match`] for each of `errors`.
+///
+/// For errors the plugin recovered from, e.g. a config that no longer parses while the previous
+/// one stays in use, so the results keep coming but the user still learns what is wrong.
+pub fn with_errors(
+    plugin: &str,
+    errors: impl IntoIterator<Item = Option<String>>,
+    matches: RVec<Match>,
+) -> RVec<Match> {
+    errors
+        .into_iter()
+        .flatten()
+        .map(|why| error_match(plugin, &why))
+        .chain(matches)
+        .collect()
+}
+
+/// Whether `selection` is a match made by [`error_match`], which there is nothing to do for.
+pub fn is_report(selection: &Match) -> bool {
+    selection.id.is_none()
+}
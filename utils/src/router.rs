@@ -0,0 +1,135 @@
+/// Where a query was routed to, and what is left of it to match against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route<'a, R> {
+    pub command: R,
+    pub query: &'a str,
+}
+
+/// Dispatches queries by their prefix, e.g. `:w firefox` or `:w ws 2`.
+///
+/// The prefix may or may not be followed by a space, `:wfire` and `:w fire` both route
+/// `fire`. A sub-command is the first word after the prefix, `:w ws 2` routes `2` to the
+/// workspace command while `:w wsl` routes `wsl` to the default one.
+#[derive(Debug, Clone)]
+pub struct Router<R> {
+    prefixes: Vec<String>,
+    require_prefix: bool,
+    commands: Vec<(String, R)>,
+    default: R,
+}
+
+impl<R: Copy> Router<R> {
+    /// Route queries starting with one of `prefixes` to `default`. If `require_prefix` is
+    /// false queries without a prefix are routed to `default` as well.
+    pub fn new(
+        prefixes: impl IntoIterator<Item = impl Into<String>>,
+        require_prefix: bool,
+        default: R,
+    ) -> Self {
+        let mut prefixes = prefixes
+            .into_iter()
+            .map(Into::into)
+            .filter(|prefix: &String| !prefix.is_empty())
+            .collect::<Vec<_>>();
+        // Try longer prefixes first, so `:ws` is not taken as `:w` followed by `s`
+        prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+
+        Self {
+            prefixes,
+            require_prefix,
+            commands: Vec::new(),
+            default,
+        }
+    }
+
+    /// Route queries whose first word after the prefix is `name` to `command`.
+    pub fn command(mut self, name: impl Into<String>, command: R) -> Self {
+        let name = name.into();
+        if !name.is_empty() {
+            self.commands.push((name, command));
+        }
+        self
+    }
+
+    pub fn route<'a>(&self, input: &'a str) -> Option<Route<'a, R>> {
+        let Some(rest) = self
+            .prefixes
+            .iter()
+            .find_map(|prefix| input.strip_prefix(prefix.as_str()))
+        else {
+            return (!self.require_prefix || self.prefixes.is_empty()).then(|| Route {
+                command: self.default,
+                query: input.trim(),
+            });
+        };
+
+        let rest = rest.trim();
+        let (word, query) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        Some(match self.commands.iter().find(|(name, _)| name == word) {
+            Some((_, command)) => Route {
+                command: *command,
+                query: query.trim_start(),
+            },
+            None => Route {
+                command: self.default,
+                query: rest,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Command {
+        Windows,
+        Workspaces,
+    }
+
+    fn route(router: &Router<Command>, input: &str) -> Option<(Command, String)> {
+        router
+            .route(input)
+            .map(|route| (route.command, route.query.to_string()))
+    }
+
+    #[test]
+    fn test_router() {
+        let router =
+            Router::new([":w", ":win"], true, Command::Windows).command("ws", Command::Workspaces);
+
+        assert_eq!(route(&router, "firefox"), None);
+        assert_eq!(
+            route(&router, ":w fire"),
+            Some((Command::Windows, "fire".into()))
+        );
+        assert_eq!(
+            route(&router, ":wfire"),
+            Some((Command::Windows, "fire".into()))
+        );
+        assert_eq!(
+            route(&router, ":win fire"),
+            Some((Command::Windows, "fire".into()))
+        );
+        assert_eq!(route(&router, ":w"), Some((Command::Windows, "".into())));
+        assert_eq!(
+            route(&router, ":w ws 2"),
+            Some((Command::Workspaces, "2".into()))
+        );
+        assert_eq!(
+            route(&router, ":w ws"),
+            Some((Command::Workspaces, "".into()))
+        );
+        assert_eq!(
+            route(&router, ":w wsl"),
+            Some((Command::Windows, "wsl".into()))
+        );
+
+        let router = Router::new([":w"], false, Command::Windows);
+        assert_eq!(
+            route(&router, " firefox "),
+            Some((Command::Windows, "firefox".into()))
+        );
+    }
+}
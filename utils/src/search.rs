@@ -0,0 +1,231 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+
+use nucleo::pattern::Pattern;
+use nucleo::{Config, Utf32Str};
+use serde::Deserialize;
+
+use crate::normalize::Normalize;
+use crate::rank::{self, TieBreak};
+use crate::{translit, MinScore, MATCHER};
+
+/// Weight of a field that should count as much as a plain `fuzzy_match`.
+pub const FULL_WEIGHT: u16 = 100;
+
+/// A named piece of text an item can be found by, e.g. an application's name or keywords.
+#[derive(Debug, Clone)]
+pub struct Field<'a> {
+    pub name: &'static str,
+    pub text: Cow<'a, str>,
+    /// Percentage the nucleo score of this field is scaled by, [`FULL_WEIGHT`] keeps it as is.
+    pub weight: u16,
+}
+
+impl<'a> Field<'a> {
+    pub fn new(name: &'static str, text: impl Into<Cow<'a, str>>, weight: u16) -> Self {
+        Self {
+            name,
+            text: text.into(),
+            weight,
+        }
+    }
+}
+
+/// Items that expose several fields to [`search`] instead of a single string.
+pub trait Searchable {
+    fn fields(&self) -> Vec<Field<'_>>;
+}
+
+impl<T: Searchable + ?Sized> Searchable for &T {
+    fn fields(&self) -> Vec<Field<'_>> {
+        (**self).fields()
+    }
+}
+
+/// How [`search_with`] matches and [`rank::sort`] orders the matches, the `search` field of
+/// every plugin config. [`search`] uses the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Matches scoring below this are left out.
+    pub min_score: MinScore,
+    /// How matches with the same score are ordered, passed to [`rank::sort`] by the plugins.
+    pub tie_breaks: Vec<TieBreak>,
+    /// Also match fields by their pinyin and latin transliteration, slightly below the
+    /// original text. Without the `transliteration` feature this does nothing.
+    pub transliterate: bool,
+    /// Unicode folding of the pattern and every field.
+    pub normalize: Normalize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            min_score: MinScore::default(),
+            tie_breaks: rank::DEFAULT_TIE_BREAKS.to_vec(),
+            transliterate: false,
+            normalize: Normalize::default(),
+        }
+    }
+}
+
+/// Match `pattern` against every field of `items`, results are sorted by score.
+///
+/// The pattern uses the fzf syntax: whitespace separated terms must all match, `'foo` matches
+/// a substring, `^foo` a prefix, `foo$` a suffix and `!foo` excludes items containing `foo`.
+/// For every term the best matching field counts fully, every other matching field adds a
+/// quarter of its weighted score, so items matching in several places rank above single hits.
+pub fn search<T: Searchable>(pattern: &str, items: impl IntoIterator<Item = T>) -> Vec<(T, u32)> {
+    search_with(pattern, items, &SearchOptions::default())
+}
+
+/// [`search`] with non-default `options`.
+pub fn search_with<T: Searchable>(
+    pattern: &str,
+    items: impl IntoIterator<Item = T>,
+    options: &SearchOptions,
+) -> Vec<(T, u32)> {
+    let mut matcher = MATCHER.lock();
+    matcher.config = Config::DEFAULT;
+    let folder = options.normalize.folder(pattern);
+    let pattern = Pattern::parse(&folder.fold(pattern), folder.case_matching());
+
+    let mut buf = Vec::new();
+    let mut items: Vec<_> = items
+        .into_iter()
+        .filter_map(|item| {
+            let mut fields = item.fields();
+            if options.transliterate {
+                transliterate(&mut fields);
+            }
+            for field in &mut fields {
+                if let Cow::Owned(text) = folder.fold(&field.text) {
+                    field.text = Cow::Owned(text);
+                }
+            }
+            let mut score = 0;
+
+            for atom in &pattern.atoms {
+                // A negative atom scores `None` on every haystack it is found in
+                if atom.negative {
+                    if fields.iter().any(|field| {
+                        atom.score(Utf32Str::new(&field.text, &mut buf), &mut matcher)
+                            .is_none()
+                    }) {
+                        return None;
+                    }
+                    continue;
+                }
+
+                let mut scores = fields
+                    .iter()
+                    .filter_map(|field| {
+                        let score =
+                            atom.score(Utf32Str::new(&field.text, &mut buf), &mut matcher)?;
+                        Some(score as u32 * field.weight as u32 / FULL_WEIGHT as u32)
+                    })
+                    .collect::<Vec<_>>();
+                scores.sort_unstable_by_key(|score| Reverse(*score));
+
+                let (best, rest) = scores.split_first()?;
+                score += best + rest.iter().sum::<u32>() / 4;
+            }
+
+            Some((item, score))
+        })
+        .collect();
+    options.min_score.retain(&mut items);
+    items.sort_by_key(|(_, score)| Reverse(*score));
+    items
+}
+
+/// Add the transliterated forms of every field as fields of their own.
+fn transliterate(fields: &mut Vec<Field<'_>>) {
+    for i in 0..fields.len() {
+        let (name, weight) = (fields[i].name, fields[i].weight);
+        for form in translit::forms(&fields[i].text).iter() {
+            fields.push(Field::new(name, form.clone(), weight * 9 / 10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct App {
+        name: &'static str,
+        keywords: &'static str,
+    }
+
+    impl Searchable for App {
+        fn fields(&self) -> Vec<Field<'_>> {
+            vec![
+                Field::new("name", self.name, FULL_WEIGHT),
+                Field::new("keywords", self.keywords, 50),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let items = vec![
+            App {
+                name: "Firefox",
+                keywords: "browser;web",
+            },
+            App {
+                name: "Web Browser",
+                keywords: "internet",
+            },
+            App {
+                name: "Files",
+                keywords: "folder;manager",
+            },
+        ];
+        let result = search("browser", &items)
+            .into_iter()
+            .map(|(app, _)| app.name)
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec!["Web Browser", "Firefox"]);
+    }
+
+    #[test]
+    fn test_search_syntax() {
+        let items = vec![
+            App {
+                name: "Firefox",
+                keywords: "browser",
+            },
+            App {
+                name: "Firefox Private Window",
+                keywords: "browser;private",
+            },
+            App {
+                name: "Fish",
+                keywords: "shell",
+            },
+        ];
+        let names = |pattern| {
+            search(pattern, &items)
+                .into_iter()
+                .map(|(app, _)| app.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("firefox !private"), vec!["Firefox"]);
+        assert_eq!(names("^fi sh$"), vec!["Fish"]);
+        assert_eq!(names("'fox win"), vec!["Firefox Private Window"]);
+        assert_eq!(names("fx"), vec!["Firefox", "Firefox Private Window"]);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let options: SearchOptions =
+            ron::from_str("(min_score: (relative: 50), normalize: (case: Ignore))").unwrap();
+        assert_eq!(options.min_score.relative, 50);
+        assert_eq!(options.normalize.case, crate::normalize::Case::Ignore);
+        assert!(options.normalize.diacritics);
+        assert_eq!(options.tie_breaks, rank::DEFAULT_TIE_BREAKS);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt, io,
+    io::Write,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::Deserialize;
+
+/// How launched programs are started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub enum Launcher {
+    /// As a detached process
+    #[default]
+    Direct,
+    /// In their own systemd scope with `systemd-run --user --scope`, so they are tracked and
+    /// resource controlled separately from anyrun
+    SystemdScope,
+    /// Prefixed with this command, e.g. `["uwsm", "app", "--"]`
+    Prefix(Vec<String>),
+}
+
+/// A program started in its own session, so it outlives anyrun and never becomes a zombie.
+///
+/// The program is double forked: anyrun only waits for the intermediate process, the program
+/// itself is adopted by init or the closest subreaper. Its stdio is `/dev/null` unless it is
+/// given [`input`](Self::input).
+#[derive(Debug, Clone)]
+pub struct Spawn {
+    program: OsString,
+    args: Vec<OsString>,
+    dir: Option<PathBuf>,
+    env: Vec<(OsString, OsString)>,
+    input: Option<Vec<u8>>,
+    launcher: Launcher,
+}
+
+impl Spawn {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            dir: None,
+            env: Vec::new(),
+            input: None,
+            launcher: Launcher::Direct,
+        }
+    }
+
+    /// Run `command` with `sh -c`.
+    pub fn shell(command: impl AsRef<OsStr>) -> Self {
+        Self::new("sh").arg("-c").arg(command)
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Working directory of the program, anyrun's by default.
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set environment variables on top of anyrun's environment.
+    pub fn envs(
+        mut self,
+        vars: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>,
+    ) -> Self {
+        self.env.extend(
+            vars.into_iter()
+                .map(|(key, value)| (key.as_ref().to_os_string(), value.as_ref().to_os_string())),
+        );
+        self
+    }
+
+    /// Pipe `input` into the program's stdin.
+    pub fn input(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+
+    pub fn launcher(mut self, launcher: &Launcher) -> Self {
+        self.launcher = launcher.clone();
+        self
+    }
+
+    /// Start the program, returning once it is running.
+    ///
+    /// Fails if the program could not be executed, e.g. because it does not exist, but not if
+    /// it exits with an error later on.
+    pub fn spawn(&self) -> Result<(), Error> {
+        let mut argv = match &self.launcher {
+            Launcher::Direct => Vec::new(),
+            Launcher::SystemdScope => ["systemd-run", "--user", "--scope", "--quiet", "--collect"]
+                .iter()
+                .map(OsString::from)
+                .collect(),
+            Launcher::Prefix(prefix) => prefix.iter().map(OsString::from).collect(),
+        };
+        argv.push(self.program.clone());
+        argv.extend(self.args.iter().cloned());
+
+        let mut command = Command::new(&argv[0]);
+        command
+            .args(&argv[1..])
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(match self.input {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        // SAFETY: only async-signal-safe functions are called between fork and exec
+        unsafe {
+            command.pre_exec(|| {
+                match libc::fork() {
+                    -1 => return Err(io::Error::last_os_error()),
+                    0 => {}
+                    _ => libc::_exit(0),
+                }
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        // The grandchild inherits the pipe std reports exec errors through, so this returns
+        // once the program itself was executed
+        let error = |why| Error {
+            program: argv[0].to_string_lossy().into_owned(),
+            why,
+        };
+        let mut child = command.spawn().map_err(error)?;
+        let written = match (&self.input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => stdin.write_all(input),
+            _ => Ok(()),
+        };
+        child.wait().map_err(error)?;
+        written.map_err(error)
+    }
+}
+
+/// A program could not be started.
+#[derive(Debug)]
+pub struct Error {
+    pub program: String,
+    pub why: io::Error,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error running `{}`: {}", self.program, self.why)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread, time::Duration, time::Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_spawn() {
+        let dir = std::env::temp_dir().join(format!("anyrun-plugins-spawn-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("out");
+
+        Spawn::shell("cat > out; echo \"$GREETING\" >> out")
+            .current_dir(&dir)
+            .envs([("GREETING", "hello")])
+            .input("input\n")
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        while fs::read_to_string(&out).unwrap_or_default() != "input\nhello\n" {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let error = Spawn::new("anyrun-plugins-missing").spawn().unwrap_err();
+        assert_eq!(error.program, "anyrun-plugins-missing");
+        assert_eq!(error.why.kind(), io::ErrorKind::NotFound);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
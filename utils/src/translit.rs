@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::LazyMutex;
+
+/// Texts whose forms are kept, so searching a long clipboard history does not grow the cache
+/// forever.
+const MAX_CACHED: usize = 4096;
+
+/// Latin forms `text` can also be found by: its romanization, and the pinyin initials of any
+/// Chinese characters in it. Ascii text has none.
+///
+/// `微信` becomes `weixin` and `wx`, `Привет` becomes `Privet`. Results are cached, item texts
+/// are transliterated on their first search only.
+pub fn forms(text: &str) -> Arc<[String]> {
+    static CACHE: LazyMutex<Cache> = LazyMutex::new(Cache::default);
+
+    if cfg!(not(feature = "transliteration")) || text.is_ascii() {
+        return Arc::new([]);
+    }
+    if let Some(forms) = CACHE.lock().get(text) {
+        return forms;
+    }
+
+    let forms: Arc<[String]> = transliterate(text).into();
+    CACHE.lock().insert(text.to_string(), forms.clone());
+    forms
+}
+
+/// Forms of the texts searched recently, up to twice [`MAX_CACHED`] of them.
+///
+/// Once `recent` is full it becomes `older`, dropping the previous `older`. A text found in
+/// `older` moves back to `recent`, so the texts of items still being searched stay cached.
+#[derive(Default)]
+struct Cache {
+    recent: HashMap<String, Arc<[String]>>,
+    older: HashMap<String, Arc<[String]>>,
+}
+
+impl Cache {
+    fn get(&mut self, text: &str) -> Option<Arc<[String]>> {
+        if let Some(forms) = self.recent.get(text) {
+            return Some(forms.clone());
+        }
+        let (text, forms) = self.older.remove_entry(text)?;
+        self.insert(text, forms.clone());
+        Some(forms)
+    }
+
+    fn insert(&mut self, text: String, forms: Arc<[String]>) {
+        if self.recent.len() >= MAX_CACHED {
+            self.older = std::mem::take(&mut self.recent);
+        }
+        self.recent.insert(text, forms);
+    }
+}
+
+#[cfg(feature = "transliteration")]
+fn transliterate(text: &str) -> Vec<String> {
+    use pinyin::ToPinyin;
+
+    let mut full = String::with_capacity(text.len());
+    let mut initials = String::new();
+    let mut chinese = false;
+    for chr in text.chars() {
+        match chr.to_pinyin() {
+            Some(pinyin) => {
+                full.push_str(pinyin.plain());
+                initials.push_str(pinyin.first_letter());
+                chinese = true;
+            }
+            None if chr.is_ascii() => {
+                full.push(chr);
+                initials.push(chr);
+            }
+            None => {
+                let latin = any_ascii::any_ascii_char(chr);
+                full.push_str(latin);
+                initials.push_str(latin);
+            }
+        }
+    }
+
+    let mut forms = vec![full];
+    if chinese {
+        forms.push(initials);
+    }
+    forms
+}
+
+#[cfg(not(feature = "transliteration"))]
+fn transliterate(_text: &str) -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "transliteration")]
+    fn test_forms() {
+        assert_eq!(&*forms("微信"), ["weixin", "wx"]);
+        assert_eq!(&*forms("中文 Input"), ["zhongwen Input", "zw Input"]);
+        assert_eq!(&*forms("Привет"), ["Privet"]);
+        assert!(forms("WeChat").is_empty());
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::default();
+        let forms: Arc<[String]> = Arc::new(["weixin".to_string()]);
+        cache.insert("微信".to_string(), forms.clone());
+        for i in 0..MAX_CACHED {
+            cache.insert(i.to_string(), Arc::new([]));
+        }
+        // Moved to `older` but still found, and back in `recent`
+        assert_eq!(cache.get("微信"), Some(forms));
+        assert!(cache.recent.len() + cache.older.len() <= 2 * MAX_CACHED);
+
+        for i in 0..2 * MAX_CACHED {
+            cache.insert(format!("new {}", i), Arc::new([]));
+        }
+        assert_eq!(cache.get("微信"), None);
+        assert!(cache.recent.len() + cache.older.len() <= 2 * MAX_CACHED);
+    }
+}
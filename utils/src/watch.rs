@@ -0,0 +1,58 @@
+use std::{ffi::OsString, io, path::Path};
+
+use inotify::{Inotify, WatchMask};
+use parking_lot::Mutex;
+
+/// Non-blocking inotify watcher of a single file.
+///
+/// The parent directory is watched instead of the file itself, so editors that save by
+/// replacing the file and files that do not exist yet are picked up as well.
+pub struct FileWatcher {
+    name: OsString,
+    inotify: Mutex<Inotify>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+            .to_os_string();
+
+        let inotify = Inotify::init()?;
+        inotify.watches().add(
+            dir,
+            WatchMask::CLOSE_WRITE
+                | WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO,
+        )?;
+
+        Ok(Self {
+            name,
+            inotify: Mutex::new(inotify),
+        })
+    }
+
+    /// Whether the file changed since the last call, never blocks.
+    pub fn changed(&self) -> bool {
+        let mut inotify = self.inotify.lock();
+        let mut buffer = [0; 1024];
+        let mut changed = false;
+
+        // Drain every queued event so the next call only sees new changes
+        while let Ok(events) = inotify.read_events(&mut buffer) {
+            let mut empty = true;
+            for event in events {
+                empty = false;
+                changed |= event.name == Some(self.name.as_os_str());
+            }
+            if empty {
+                break;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use nucleo::pattern::CaseMatching;
+use nucleo::{Config, Nucleo};
+use parking_lot::Mutex;
+
+use crate::{Searchable, MATCHER};
+
+/// Multi-threaded matcher for large item sets, built on nucleo's background worker.
+///
+/// The worker only keeps the searchable text of every item, results refer to items by
+/// their position in the list the worker was built from. Fields are matched as a single
+/// text, so field weights do not apply.
+pub struct Worker {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    nucleo: Nucleo<u32>,
+    query: String,
+}
+
+impl Worker {
+    pub fn new<T: Searchable>(items: impl IntoIterator<Item = T>) -> Self {
+        let worker = Self {
+            inner: Mutex::new(Inner {
+                nucleo: Nucleo::new(Config::DEFAULT, Arc::new(|| {}), None, 1),
+                query: String::new(),
+            }),
+        };
+        worker.replace(items);
+        worker
+    }
+
+    /// Replace all items, the next search starts from scratch.
+    pub fn replace<T: Searchable>(&self, items: impl IntoIterator<Item = T>) {
+        let mut inner = self.inner.lock();
+        inner.nucleo.restart(true);
+        inner.query.clear();
+
+        let injector = inner.nucleo.injector();
+        for (i, item) in items.into_iter().enumerate() {
+            let text = item
+                .fields()
+                .iter()
+                .map(|field| field.text.as_ref())
+                .collect::<Vec<_>>()
+                .join(" ");
+            injector.push(i as u32, |columns| columns[0] = text.into());
+        }
+    }
+
+    /// Positions and scores of the `limit` best matches of `pattern`, best first.
+    ///
+    /// When `pattern` extends the previous one only the previous matches are searched again.
+    pub fn search(&self, pattern: &str, limit: usize) -> Vec<(usize, u32)> {
+        let mut inner = self.inner.lock();
+        // An escaped space could join the old last term with the new one
+        let append = pattern.starts_with(&inner.query) && !inner.query.ends_with('\\');
+        inner
+            .nucleo
+            .pattern
+            .reparse(0, pattern, CaseMatching::Smart, append);
+        inner.query = pattern.to_string();
+
+        while inner.nucleo.tick(10).running {}
+
+        // The snapshot does not expose scores, rescoring the few returned items is cheap
+        let mut matcher = MATCHER.lock();
+        matcher.config = Config::DEFAULT;
+        let snapshot = inner.nucleo.snapshot();
+        let count = snapshot.matched_item_count().min(limit as u32);
+        snapshot
+            .matched_items(..count)
+            .map(|item| {
+                let score = snapshot
+                    .pattern()
+                    .score(item.matcher_columns, &mut matcher)
+                    .unwrap_or_default();
+                (*item.data as usize, score)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{search, Field, FULL_WEIGHT};
+
+    struct Name(&'static str);
+
+    impl Searchable for Name {
+        fn fields(&self) -> Vec<Field<'_>> {
+            vec![Field::new("name", self.0, FULL_WEIGHT)]
+        }
+    }
+
+    #[test]
+    fn test_worker() {
+        let names = [
+            "LATIN SMALL LETTER A",
+            "GREEK SMALL LETTER ALPHA",
+            "RIGHTWARDS ARROW",
+            "LEFTWARDS ARROW",
+        ]
+        .map(Name);
+        let worker = Worker::new(&names);
+
+        assert_eq!(worker.search("arrow", 10).len(), 2);
+        // Extending the query narrows the previous matches
+        assert_eq!(worker.search("arrow left", 10), vec![(3, 254)]);
+        assert_eq!(worker.search("letter", 1).len(), 1);
+
+        // Scores are the same as the single threaded search
+        let expected = search("small", &names)
+            .into_iter()
+            .map(|(name, score)| (name.0, score))
+            .collect::<Vec<_>>();
+        let result = worker
+            .search("small", 10)
+            .into_iter()
+            .map(|(i, score)| (names[i].0, score))
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected);
+    }
+}